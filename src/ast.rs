@@ -1,25 +1,197 @@
+use num::Num;
+use std::fmt;
+
+/// Identifies a source file. The interpreter currently reads one file, which
+/// is always file 0.
+pub type FileId = usize;
+
+/// A region of source text: the bytes `start..end` of file `file`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+/// A term together with the span of source it was parsed from.
+///
+/// Spans are location metadata only: two terms are equal, and print the
+/// same, whenever their `kind`s are.
+#[derive(Clone)]
+pub struct Term {
+    pub kind: TermKind,
+    pub span: Span,
+}
+
+impl Term {
+    pub fn new(kind: TermKind, span: Span) -> Term {
+        Term { kind, span }
+    }
+
+    /// The terms directly within `self`, in source order.
+    pub fn subterms(&self) -> Vec<&Term> {
+        match &self.kind {
+            TermKind::Var(_)
+            | TermKind::NumConst(_)
+            | TermKind::BoolConst(_)
+            | TermKind::StrConst(_)
+            | TermKind::Data { .. } => vec![],
+            TermKind::Lambda { expr: t, .. }
+            | TermKind::Project { tuple: t, .. }
+            | TermKind::Field { record: t, .. }
+            | TermKind::Negate(t)
+            | TermKind::Not(t)
+            | TermKind::Assignm { expr: t, .. } => vec![t],
+            TermKind::Apply { var_term, function } => vec![function, var_term],
+            TermKind::Let { value, body, .. } => vec![value, body],
+            TermKind::LetRec { bindings, body } => {
+                let mut terms: Vec<&Term> = bindings.iter().map(|(_, value)| value).collect();
+                terms.push(body);
+                terms
+            }
+            TermKind::Tuple(components) | TermKind::List(components) => components.iter().collect(),
+            TermKind::Cons { head, tail } => vec![head, tail],
+            TermKind::Record(fields) => fields.iter().map(|(_, value)| value).collect(),
+            TermKind::MathOp { t1, t2, .. } => vec![t1, t2],
+            TermKind::Concat {
+                left_side,
+                right_side,
+            }
+            | TermKind::Equals {
+                left_side,
+                right_side,
+            }
+            | TermKind::NotEquals {
+                left_side,
+                right_side,
+            }
+            | TermKind::Compare {
+                left_side,
+                right_side,
+                ..
+            }
+            | TermKind::Logic {
+                left_side,
+                right_side,
+                ..
+            } => vec![left_side, right_side],
+            TermKind::IfStmt {
+                test,
+                then_body,
+                else_body,
+            } => vec![test, then_body, else_body],
+            TermKind::Case { scrutinee, arms } => {
+                let mut terms = vec![&**scrutinee];
+                terms.extend(arms.iter().map(|arm| &arm.body));
+                terms
+            }
+            TermKind::Match { scrutinee, arms } => {
+                let mut terms = vec![&**scrutinee];
+                terms.extend(arms.iter().map(|arm| &arm.body));
+                terms
+            }
+        }
+    }
+}
+
+/// Builds a term with an empty span, for terms that do not come from source.
+impl From<TermKind> for Term {
+    fn from(kind: TermKind) -> Term {
+        Term::new(kind, Span::default())
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Term) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Eq for Term {}
+
+impl fmt::Debug for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
-pub enum Term {
+pub enum TermKind {
     Var(String),
     Lambda {
         var_name: String,
+        var_type: Option<TypeAnn>,
         expr: Box<Term>,
     },
     Apply {
         var_term: Box<Term>,
         function: Box<Term>,
     },
+    /// `let name = value in body`: `body` with `name` bound to `value`.
+    Let {
+        name: String,
+        value: Box<Term>,
+        body: Box<Term>,
+    },
+    /// `let rec f = value and ... in body`: every value can refer to every
+    /// name of the group, including its own.
+    LetRec {
+        bindings: Vec<(String, Term)>,
+        body: Box<Term>,
+    },
 
     /* Constants */
-    NumConst(u64),
+    NumConst(Num),
     BoolConst(bool),
+    /// A string literal, with its escapes replaced by the characters they
+    /// stand for.
+    StrConst(String),
+
+    /// `(a, b, ...)`, with at least two components.
+    Tuple(Vec<Term>),
+    /// `tuple.index`: the component of `tuple` at `index`, counting from 0.
+    Project {
+        tuple: Box<Term>,
+        index: usize,
+    },
+    /// `{ name = value, ... }`, with the fields in source order.
+    Record(Vec<(String, Term)>),
+    /// `record.field`.
+    Field {
+        record: Box<Term>,
+        field: String,
+    },
+    /// `[a, b, ...]`, which may be empty.
+    List(Vec<Term>),
+    /// `head :: tail`: the list `tail` with `head` in front.
+    Cons {
+        head: Box<Term>,
+        tail: Box<Term>,
+    },
 
     /* Operations */
+    Negate(Box<Term>),
+    Not(Box<Term>),
     MathOp {
         opr: BinMathOp,
         t1: Box<Term>,
         t2: Box<Term>,
     },
+    /// `left_side ++ right_side`: two strings joined together.
+    Concat {
+        left_side: Box<Term>,
+        right_side: Box<Term>,
+    },
     IfStmt {
         test: Box<Term>,
         then_body: Box<Term>,
@@ -33,11 +205,168 @@ pub enum Term {
         left_side: Box<Term>,
         right_side: Box<Term>,
     },
+    Compare {
+        opr: CompareOp,
+        left_side: Box<Term>,
+        right_side: Box<Term>,
+    },
+    /// `&&` or `||`, which only evaluate `right_side` when they need to.
+    Logic {
+        opr: LogicOp,
+        left_side: Box<Term>,
+        right_side: Box<Term>,
+    },
+
+    /// `case scrutinee of Con x y -> body | ... end`.
+    Case {
+        scrutinee: Box<Term>,
+        arms: Vec<CaseArm>,
+    },
+    /// `match scrutinee with | pattern -> body | ... end`: the body of the
+    /// first arm whose pattern matches.
+    Match {
+        scrutinee: Box<Term>,
+        arms: Vec<MatchArm>,
+    },
 
     Assignm {
         var_name: String,
         expr: Box<Term>,
     },
+    /// `data Name = Con Type ... | ...;`, with the constructors in source
+    /// order.
+    Data {
+        name: String,
+        constructors: Vec<(String, Vec<TypeAnn>)>,
+    },
+}
+
+/// An arm of a `case`: `constructor vars -> body`.
+///
+/// Like that of a term, the span of the pattern is ignored by comparisons
+/// and when printing.
+#[derive(Clone)]
+pub struct CaseArm {
+    pub constructor: String,
+    pub vars: Vec<String>,
+    pub body: Term,
+    /// The span of the pattern, `constructor vars`.
+    pub span: Span,
+}
+
+impl PartialEq for CaseArm {
+    fn eq(&self, other: &CaseArm) -> bool {
+        (&self.constructor, &self.vars, &self.body)
+            == (&other.constructor, &other.vars, &other.body)
+    }
+}
+
+impl Eq for CaseArm {}
+
+impl fmt::Debug for CaseArm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CaseArm")
+            .field("constructor", &self.constructor)
+            .field("vars", &self.vars)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// An arm of a `match`: `pattern -> body`. As with `CaseArm`, the span of
+/// the pattern is ignored by comparisons and when printing.
+#[derive(Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Term,
+    pub span: Span,
+}
+
+impl PartialEq for MatchArm {
+    fn eq(&self, other: &MatchArm) -> bool {
+        (&self.pattern, &self.body) == (&other.pattern, &other.body)
+    }
+}
+
+impl Eq for MatchArm {}
+
+impl fmt::Debug for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MatchArm")
+            .field("pattern", &self.pattern)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// What a `match` arm compares its value with. Patterns print as they are
+/// written, with lists of a fixed length in brackets: `[x, _]`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Pattern {
+    Num(Num),
+    Bool(bool),
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A name, which matches anything and is bound to it in the arm.
+    Var(String),
+    /// `[]`, the empty list.
+    Nil,
+    /// `head :: tail`, a list that is not empty. `[a, b]` is short for
+    /// `a :: b :: []`.
+    Cons(Box<Pattern>, Box<Pattern>),
+}
+
+impl Pattern {
+    /// Adds the names that `self` binds to `vars`.
+    pub fn vars(&self, vars: &mut Vec<String>) {
+        match self {
+            Pattern::Var(name) => vars.push(name.clone()),
+            Pattern::Cons(head, tail) => {
+                head.vars(vars);
+                tail.vars(vars);
+            }
+            Pattern::Num(_) | Pattern::Bool(_) | Pattern::Wildcard | Pattern::Nil => {}
+        }
+    }
+
+    /// The patterns of the elements, if `self` matches lists of one length.
+    fn elements(&self) -> Option<Vec<&Pattern>> {
+        match self {
+            Pattern::Nil => Some(Vec::new()),
+            Pattern::Cons(head, tail) => {
+                let mut elements = tail.elements()?;
+                elements.insert(0, head);
+                Some(elements)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Num(n) => write!(f, "{}", n),
+            Pattern::Bool(b) => write!(f, "{}", b),
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Var(name) => write!(f, "{}", name),
+            Pattern::Nil => write!(f, "[]"),
+            Pattern::Cons(..) if self.elements().is_some() => {
+                let elements: Vec<String> = self
+                    .elements()
+                    .unwrap()
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            // `::` associates to the right, so only a head needs brackets.
+            Pattern::Cons(head, tail) => match **head {
+                Pattern::Cons(..) => write!(f, "({}) :: {}", head, tail),
+                _ => write!(f, "{} :: {}", head, tail),
+            },
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -46,4 +375,91 @@ pub enum BinMathOp {
     Minus,
     Multiply,
     Divide,
+    /// The remainder of `Divide`, which has the sign of the dividend.
+    Modulo,
+    Power,
+}
+
+impl fmt::Display for BinMathOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinMathOp::Add => "+",
+            BinMathOp::Minus => "-",
+            BinMathOp::Multiply => "*",
+            BinMathOp::Divide => "/",
+            BinMathOp::Modulo => "%",
+            BinMathOp::Power => "^",
+        };
+        f.write_str(symbol)
+    }
+}
+
+/// An ordering comparison between two integers.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum CompareOp {
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            CompareOp::Less => "<",
+            CompareOp::LessEq => "<=",
+            CompareOp::Greater => ">",
+            CompareOp::GreaterEq => ">=",
+        };
+        f.write_str(symbol)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum LogicOp {
+    And,
+    Or,
+}
+
+impl fmt::Display for LogicOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            LogicOp::And => "&&",
+            LogicOp::Or => "||",
+        };
+        f.write_str(symbol)
+    }
+}
+
+/// How a program writes arrows, in function types and in arms: `->` or `→`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ArrowStyle {
+    #[default]
+    Ascii,
+    Unicode,
+}
+
+impl fmt::Display for ArrowStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ArrowStyle::Ascii => "->",
+            ArrowStyle::Unicode => "→",
+        })
+    }
+}
+
+/// A type as written in the source, such as the annotation on a lambda
+/// parameter.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TypeAnn {
+    Int,
+    Bool,
+    Str,
+    Arrow(Box<TypeAnn>, Box<TypeAnn>),
+    Tuple(Vec<TypeAnn>),
+    Record(Vec<(String, TypeAnn)>),
+    /// `List T`, the type of lists of `T`s.
+    List(Box<TypeAnn>),
+    /// A type declared with `data`.
+    Named(String),
 }
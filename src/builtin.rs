@@ -0,0 +1,126 @@
+//! Functions that every program can use without defining them.
+
+use type_check::{Class, Scheme, TermType};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Builtin {
+    Min,
+    Max,
+    Abs,
+    /// The first component of a pair.
+    Fst,
+    /// The second component of a pair.
+    Snd,
+    /// The first element of a list that is not empty.
+    Head,
+    /// A list that is not empty, without its first element.
+    Tail,
+    IsEmpty,
+    /// The number of elements of a list, or of characters of a string.
+    Length,
+    /// An integer or a boolean as a string.
+    Show,
+}
+
+pub const BUILTINS: [Builtin; 10] = [
+    Builtin::Min,
+    Builtin::Max,
+    Builtin::Abs,
+    Builtin::Fst,
+    Builtin::Snd,
+    Builtin::Head,
+    Builtin::Tail,
+    Builtin::IsEmpty,
+    Builtin::Length,
+    Builtin::Show,
+];
+
+impl Builtin {
+    /// Looks up the builtin called `name`.
+    pub fn named(name: &str) -> Option<Builtin> {
+        BUILTINS.iter().cloned().find(|b| b.name() == name)
+    }
+
+    /// The name programs refer to the builtin by. A program may still define
+    /// its own function with the same name, which then hides the builtin.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Abs => "abs",
+            Builtin::Fst => "fst",
+            Builtin::Snd => "snd",
+            Builtin::Head => "head",
+            Builtin::Tail => "tail",
+            Builtin::IsEmpty => "isEmpty",
+            Builtin::Length => "length",
+            Builtin::Show => "show",
+        }
+    }
+
+    /// How many arguments the builtin takes before it is evaluated.
+    pub fn arity(self) -> usize {
+        match self {
+            Builtin::Min | Builtin::Max => 2,
+            _ => 1,
+        }
+    }
+
+    /// The arithmetic builtins take integers and return an integer; `fst`
+    /// and `snd` work on pairs of any types, and the list builtins on lists
+    /// of any type. `length` also counts the characters of a string, and
+    /// `show` takes an integer or a boolean.
+    pub fn scheme(self) -> Scheme {
+        let of_class = |class, result| {
+            let ty = TermType::Arrow(Box::new(TermType::Var(0)), Box::new(result));
+            (vec![0], vec![(0, class)], ty)
+        };
+        let pair = || TermType::Tuple(vec![TermType::Var(0), TermType::Var(1)]);
+        let list = || TermType::List(Box::new(TermType::Var(0)));
+        let from_list = |ty| {
+            let ty = TermType::Arrow(Box::new(list()), Box::new(ty));
+            (vec![0], Vec::new(), ty)
+        };
+        let (vars, classes, ty) = match self {
+            Builtin::Fst => (
+                vec![0, 1],
+                Vec::new(),
+                TermType::Arrow(Box::new(pair()), Box::new(TermType::Var(0))),
+            ),
+            Builtin::Snd => (
+                vec![0, 1],
+                Vec::new(),
+                TermType::Arrow(Box::new(pair()), Box::new(TermType::Var(1))),
+            ),
+            Builtin::Head => from_list(TermType::Var(0)),
+            Builtin::Tail => from_list(list()),
+            Builtin::IsEmpty => from_list(TermType::Bool),
+            Builtin::Length => of_class(Class::Sized, TermType::Int),
+            Builtin::Show => of_class(Class::Showable, TermType::Str),
+            _ => {
+                let ty = (0..self.arity()).fold(TermType::Int, |acc, _| {
+                    TermType::Arrow(Box::new(TermType::Int), Box::new(acc))
+                });
+                (Vec::new(), Vec::new(), ty)
+            }
+        };
+        Scheme {
+            vars,
+            eq_vars: Vec::new(),
+            classes,
+            ty,
+        }
+    }
+}
+
+#[test]
+fn test_scheme() {
+    assert_eq!("Int -> Int -> Int", Builtin::Max.scheme().ty.to_string());
+    assert_eq!("Int -> Int", Builtin::Abs.scheme().ty.to_string());
+    assert_eq!("(a, b) -> b", Builtin::Snd.scheme().ty.to_string());
+    assert_eq!("List a -> List a", Builtin::Tail.scheme().ty.to_string());
+    assert_eq!(vec![(0, Class::Showable)], Builtin::Show.scheme().classes);
+    assert_eq!(Some(Builtin::IsEmpty), Builtin::named("isEmpty"));
+    assert_eq!(Some(Builtin::Min), Builtin::named("min"));
+    assert_eq!(None, Builtin::named("mini"));
+}
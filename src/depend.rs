@@ -0,0 +1,519 @@
+//! The order in which top-level statements are checked and evaluated.
+//!
+//! A definition may be used before it is defined, so statements are ordered
+//! by the definitions they use instead of by their place in the file. A data
+//! declaration defines its type and its constructors. A name can be assigned
+//! again, and the statements that follow the new assignment use it instead.
+
+use ast::{Term, TermKind, TypeAnn};
+use std::collections::{BTreeSet, HashMap};
+use type_check::{TypeError, E_DUPLICATE_DEFINITION, E_RECURSIVE_VALUE};
+
+/// A strongly connected component of the graph of top-level statements, in
+/// which each statement points at the definitions it uses.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Component {
+    /// The index of a statement that does not use itself.
+    Single(usize),
+    /// The indices of definitions that use each other, or of a single
+    /// definition that uses itself, in file order.
+    Recursive(Vec<usize>),
+}
+
+/// Orders `terms` so that every definition comes before the statements that
+/// use it, keeping the statements in file order where they do not depend on
+/// each other.
+pub fn order(terms: &[Term]) -> Result<Vec<Component>, TypeError> {
+    // Values and types have separate names. A value that is assigned more
+    // than once has each of its assignments, in file order.
+    let mut definitions: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut types = HashMap::new();
+    for (i, term) in terms.iter().enumerate() {
+        match &term.kind {
+            TermKind::Assignm { var_name, .. } => match definitions.get_mut(var_name.as_str()) {
+                Some(assignments) if is_assignment(&terms[assignments[0]]) => assignments.push(i),
+                _ => define(&mut definitions, var_name, i, term)?,
+            },
+            TermKind::Data { name, constructors } => {
+                define(&mut types, name, i, term)?;
+                for (constructor, _) in constructors {
+                    define(&mut definitions, constructor, i, term)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let resolved: Vec<Vec<usize>> = terms
+        .iter()
+        .enumerate()
+        .map(|(i, term)| {
+            let mut free = BTreeSet::new();
+            free_vars(term, &mut Vec::new(), &mut free);
+            // A data declaration only names types.
+            let is_data = matches!(term.kind, TermKind::Data { .. });
+            free.iter()
+                .filter_map(|name| {
+                    let definition = definitions.get(name.as_str()).filter(|_| !is_data);
+                    definition
+                        .or_else(|| types.get(name.as_str()))
+                        .map(|definitions| resolve(definitions, i))
+                })
+                .collect()
+        })
+        .collect();
+
+    // An assignment to a name that is already assigned replaces the earlier
+    // value, so it comes after the earlier assignment and everything that
+    // uses it.
+    let mut uses = resolved.clone();
+    for assignments in definitions.values() {
+        for pair in assignments.windows(2) {
+            let (earlier, later) = (pair[0], pair[1]);
+            uses[later].push(earlier);
+            uses[later]
+                .extend((0..terms.len()).filter(|&u| u != later && resolved[u].contains(&earlier)));
+        }
+    }
+    for uses in &mut uses {
+        uses.sort_unstable();
+        uses.dedup();
+    }
+
+    let mut tarjan = Tarjan {
+        uses,
+        index: vec![None; terms.len()],
+        low: vec![0; terms.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; terms.len()],
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for i in 0..terms.len() {
+        if tarjan.index[i].is_none() {
+            tarjan.visit(i);
+        }
+    }
+    for component in &tarjan.components {
+        if let Component::Recursive(members) = component {
+            // Data types can refer to each other freely, and since they only
+            // refer to types, they are never grouped with other definitions.
+            if !matches!(terms[members[0]].kind, TermKind::Data { .. }) {
+                check_reassignments(terms, members, &definitions, &resolved)?;
+                check_functions(terms, members)?;
+            }
+        }
+    }
+    Ok(tarjan.components)
+}
+
+fn is_assignment(term: &Term) -> bool {
+    matches!(term.kind, TermKind::Assignm { .. })
+}
+
+/// Records that `term`, the statement at index `i`, defines `name`, which
+/// only an assignment can do more than once.
+fn define<'a>(
+    names: &mut HashMap<&'a str, Vec<usize>>,
+    name: &'a str,
+    i: usize,
+    term: &Term,
+) -> Result<(), TypeError> {
+    match names.insert(name, vec![i]) {
+        None => Ok(()),
+        Some(_) => Err(TypeError::new(
+            E_DUPLICATE_DEFINITION,
+            format!("`{}` is defined more than once", name),
+            term.span,
+        )
+        .with_help("data types and constructors can only be defined once")),
+    }
+}
+
+/// The definition that the statement at index `i` uses, out of the
+/// `definitions` of a name: the last one before the statement, or the first
+/// one if the statement comes before them all.
+fn resolve(definitions: &[usize], i: usize) -> usize {
+    *definitions
+        .iter()
+        .rev()
+        .find(|&&d| d < i)
+        .unwrap_or(&definitions[0])
+}
+
+/// Checks that no assignment in a group of definitions that refer to each
+/// other replaces a value that the group still uses, since the group can
+/// only see one value for each name.
+fn check_reassignments(
+    terms: &[Term],
+    members: &[usize],
+    definitions: &HashMap<&str, Vec<usize>>,
+    resolved: &[Vec<usize>],
+) -> Result<(), TypeError> {
+    for &i in members {
+        if let TermKind::Assignm { var_name, .. } = &terms[i].kind {
+            let assignments = &definitions[var_name.as_str()];
+            let earlier = match assignments.iter().position(|&d| d == i) {
+                Some(0) | None => continue,
+                Some(n) => assignments[n - 1],
+            };
+            if members
+                .iter()
+                .any(|&u| u == earlier || (u != i && resolved[u].contains(&earlier)))
+            {
+                return Err(TypeError::new(
+                    E_DUPLICATE_DEFINITION,
+                    format!(
+                        "`{}` is assigned again in a group of definitions that uses its earlier \
+                         value",
+                        var_name
+                    ),
+                    terms[i].span,
+                )
+                .with_help("give one of the assignments another name"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that definitions that refer to each other are all functions,
+/// since only a function can be called before it has a value.
+fn check_functions(terms: &[Term], members: &[usize]) -> Result<(), TypeError> {
+    let names: Vec<String> = members
+        .iter()
+        .map(|&i| match &terms[i].kind {
+            TermKind::Assignm { var_name, .. } => format!("`{}`", var_name),
+            _ => unreachable!(),
+        })
+        .collect();
+    for (&i, name) in members.iter().zip(&names) {
+        if let TermKind::Assignm { expr, .. } = &terms[i].kind {
+            if let TermKind::Lambda { .. } = expr.kind {
+                continue;
+            }
+            let help = match names.split_last() {
+                Some((_, [])) => "only lambdas can refer to themselves".to_string(),
+                Some((last, rest)) => format!(
+                    "{} and {} refer to each other, and only lambdas can refer to themselves",
+                    rest.join(", "),
+                    last
+                ),
+                None => unreachable!(),
+            };
+            return Err(TypeError::new(
+                E_RECURSIVE_VALUE,
+                format!("{} is defined recursively, so it must be a function", name),
+                expr.span,
+            )
+            .with_help(help));
+        }
+    }
+    Ok(())
+}
+
+/// Tarjan's algorithm, which finishes a component only after every
+/// component it uses.
+struct Tarjan {
+    uses: Vec<Vec<usize>>,
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next_index: usize,
+    components: Vec<Component>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, v: usize) {
+        self.index[v] = Some(self.next_index);
+        self.low[v] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+
+        for w in self.uses[v].clone() {
+            match self.index[w] {
+                None => {
+                    self.visit(w);
+                    self.low[v] = self.low[v].min(self.low[w]);
+                }
+                Some(index) if self.on_stack[w] => self.low[v] = self.low[v].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low[v]) == self.index[v] {
+            let mut members = Vec::new();
+            loop {
+                let w = self.stack.pop().unwrap();
+                self.on_stack[w] = false;
+                members.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            members.sort_unstable();
+            let component = if members.len() == 1 && !self.uses[v].contains(&v) {
+                Component::Single(v)
+            } else {
+                Component::Recursive(members)
+            };
+            self.components.push(component);
+        }
+    }
+}
+
+/// Adds the variables of `term` that are not bound within it, or by
+/// `bound`, to `free`, together with the constructors its `case`s match on
+/// and the data types its annotations name.
+fn free_vars(term: &Term, bound: &mut Vec<String>, free: &mut BTreeSet<String>) {
+    match &term.kind {
+        TermKind::Var(name) => {
+            if !bound.contains(name) {
+                free.insert(name.clone());
+            }
+        }
+        TermKind::Lambda {
+            var_name,
+            var_type,
+            expr,
+        } => {
+            if let Some(ann) = var_type {
+                type_names(ann, free);
+            }
+            bound.push(var_name.clone());
+            free_vars(expr, bound, free);
+            bound.pop();
+        }
+        TermKind::Let { name, value, body } => {
+            free_vars(value, bound, free);
+            bound.push(name.clone());
+            free_vars(body, bound, free);
+            bound.pop();
+        }
+        TermKind::LetRec { bindings, body } => {
+            bound.extend(bindings.iter().map(|(name, _)| name.clone()));
+            for (_, value) in bindings {
+                free_vars(value, bound, free);
+            }
+            free_vars(body, bound, free);
+            let len = bound.len() - bindings.len();
+            bound.truncate(len);
+        }
+        TermKind::NumConst(_) | TermKind::BoolConst(_) | TermKind::StrConst(_) => {}
+        TermKind::Tuple(components) | TermKind::List(components) => {
+            for component in components {
+                free_vars(component, bound, free);
+            }
+        }
+        TermKind::Record(fields) => {
+            for (_, value) in fields {
+                free_vars(value, bound, free);
+            }
+        }
+        TermKind::Negate(t)
+        | TermKind::Not(t)
+        | TermKind::Project { tuple: t, .. }
+        | TermKind::Field { record: t, .. } => free_vars(t, bound, free),
+        TermKind::Assignm { expr, .. } => free_vars(expr, bound, free),
+        TermKind::Data { constructors, .. } => {
+            for (_, fields) in constructors {
+                for field in fields {
+                    type_names(field, free);
+                }
+            }
+        }
+        TermKind::Match { scrutinee, arms } => {
+            free_vars(scrutinee, bound, free);
+            for arm in arms {
+                let len = bound.len();
+                arm.pattern.vars(bound);
+                free_vars(&arm.body, bound, free);
+                bound.truncate(len);
+            }
+        }
+        TermKind::Case { scrutinee, arms } => {
+            free_vars(scrutinee, bound, free);
+            for arm in arms {
+                if !bound.contains(&arm.constructor) {
+                    free.insert(arm.constructor.clone());
+                }
+                bound.extend(arm.vars.iter().cloned());
+                free_vars(&arm.body, bound, free);
+                let len = bound.len() - arm.vars.len();
+                bound.truncate(len);
+            }
+        }
+        TermKind::Apply {
+            var_term: t1,
+            function: t2,
+        }
+        | TermKind::Cons { head: t1, tail: t2 }
+        | TermKind::MathOp { t1, t2, .. }
+        | TermKind::Concat {
+            left_side: t1,
+            right_side: t2,
+        }
+        | TermKind::Equals {
+            left_side: t1,
+            right_side: t2,
+        }
+        | TermKind::NotEquals {
+            left_side: t1,
+            right_side: t2,
+        }
+        | TermKind::Compare {
+            left_side: t1,
+            right_side: t2,
+            ..
+        }
+        | TermKind::Logic {
+            left_side: t1,
+            right_side: t2,
+            ..
+        } => {
+            free_vars(t1, bound, free);
+            free_vars(t2, bound, free);
+        }
+        TermKind::IfStmt {
+            test,
+            then_body,
+            else_body,
+        } => {
+            free_vars(test, bound, free);
+            free_vars(then_body, bound, free);
+            free_vars(else_body, bound, free);
+        }
+    }
+}
+
+/// Adds the names of the data types in `ann` to `names`.
+fn type_names(ann: &TypeAnn, names: &mut BTreeSet<String>) {
+    match ann {
+        TypeAnn::Int | TypeAnn::Bool | TypeAnn::Str => {}
+        TypeAnn::Arrow(from, to) => {
+            type_names(from, names);
+            type_names(to, names);
+        }
+        TypeAnn::Tuple(components) => {
+            for component in components {
+                type_names(component, names);
+            }
+        }
+        TypeAnn::Record(fields) => {
+            for (_, field) in fields {
+                type_names(field, names);
+            }
+        }
+        TypeAnn::List(element) => type_names(element, names),
+        TypeAnn::Named(name) => {
+            names.insert(name.clone());
+        }
+    }
+}
+
+#[test]
+fn test_free_vars() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "(\\x. let y = x + z in y w);\
+         let rec f = (\\n. g (f n)) in f h;",
+    )
+    .unwrap();
+    let free = |term| {
+        let mut free = BTreeSet::new();
+        free_vars(term, &mut Vec::new(), &mut free);
+        free.into_iter().collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["w", "z"], free(&terms[0]));
+    assert_eq!(vec!["g", "h"], free(&terms[1]));
+}
+
+#[test]
+fn test_order() {
+    use self::Component::*;
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "b;\
+         a := 1;\
+         b := (\\n. a + c n);\
+         c := (\\n. b n);\
+         even := (\\n. n == 0 || odd (n - 1));\
+         odd := (\\n. n != 0 && even (n - 1));\
+         loop := (\\n. loop n);\
+         a + 1;",
+    )
+    .unwrap();
+    assert_eq!(
+        Ok(vec![
+            Single(1),
+            Recursive(vec![2, 3]),
+            Single(0),
+            Recursive(vec![4, 5]),
+            Recursive(vec![6]),
+            Single(7)
+        ]),
+        order(&terms)
+    );
+
+    let terms = parse_file(0, "a := b;\nb := (\\x. c);\nc := a + 1;").unwrap();
+    let error = order(&terms).unwrap_err();
+    assert_eq!(
+        (
+            5,
+            "`a` is defined recursively, so it must be a function".to_string(),
+            Some(
+                "`a`, `b` and `c` refer to each other, and only lambdas can refer to themselves"
+                    .to_string()
+            )
+        ),
+        (error.span.start, error.message, error.help)
+    );
+
+    // A new assignment comes after the earlier one and its uses, and the
+    // statements that follow it use it instead.
+    let terms = parse_file(0, "x;\nx := 1;\nf := (\\n. x);\nx := x + 1;\nx;").unwrap();
+    assert_eq!(
+        Ok(vec![Single(1), Single(0), Single(2), Single(3), Single(4)]),
+        order(&terms)
+    );
+    let terms = parse_file(0, "x := 2;\nx := 3;\nx - 1;").unwrap();
+    assert_eq!(Ok(vec![Single(0), Single(1), Single(2)]), order(&terms));
+
+    let terms = parse_file(0, "x := 1;\ng := (\\n. x + h n);\nx := 2;\nh := (\\n. x);").unwrap();
+    let error = order(&terms).unwrap_err();
+    assert_eq!(E_DUPLICATE_DEFINITION, error.code);
+    assert_eq!(28, error.span.start);
+}
+
+#[test]
+fn test_order_data() {
+    use self::Component::*;
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "area (Square 2);\
+         area := \\s: Shape. case s of Square n -> n * n end;\
+         data Shape = Square Int;\
+         data A = A B;\
+         data B = B A | NoB;",
+    )
+    .unwrap();
+    assert_eq!(
+        Ok(vec![Single(2), Single(1), Single(0), Recursive(vec![3, 4])]),
+        order(&terms)
+    );
+
+    let terms = parse_file(0, "data T = A | B;\nB := 1;").unwrap();
+    let error = order(&terms).unwrap_err();
+    assert_eq!(E_DUPLICATE_DEFINITION, error.code);
+    assert_eq!(16, error.span.start);
+    let terms = parse_file(0, "data T = A;\ndata T = B;").unwrap();
+    assert_eq!(12, order(&terms).unwrap_err().span.start);
+}
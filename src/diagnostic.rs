@@ -0,0 +1,260 @@
+use ast::Span;
+use eval::EvalError;
+use exhaustive::Warning;
+use input::line_col;
+use parse::ParseError;
+use std::fmt::Write;
+use type_check::TypeError;
+
+/// The stage of the interpreter that rejected a program.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Phase {
+    Parse,
+    Type,
+    Runtime,
+}
+
+impl Phase {
+    /// The process exit code used when a program fails in this phase.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Phase::Parse => 2,
+            Phase::Type => 3,
+            Phase::Runtime => 4,
+        }
+    }
+}
+
+/// Whether a diagnostic stops the program.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// An error or a warning in a program, ready to be shown to the user next to
+/// the source that caused it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// The stage that found the problem. For an error, this is the stage
+    /// that rejected the program.
+    pub phase: Phase,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic in the style of rustc: a header with the level
+    /// and code, the location, the offending source line with the span
+    /// underlined, and a help note if there is one.
+    pub fn render(&self, source: &str, filename: &str) -> String {
+        let (line, column) = line_col(source, self.span.start);
+        let line_start = source[..self.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_text = source[line_start..].lines().next().unwrap_or("");
+
+        // Underline the span, stopping at the end of its first line. Empty
+        // spans, such as the end of the file, still get one caret.
+        let underlined = &source[self.span.start..self.span.end.max(self.span.start)];
+        let width = underlined
+            .lines()
+            .next()
+            .map_or(0, |l| l.chars().count())
+            .max(1);
+
+        let gutter = " ".repeat(line.to_string().len());
+        let mut out = String::new();
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        writeln!(out, "{}[{}]: {}", level, self.code, self.message).unwrap();
+        writeln!(out, "{}--> {}:{}:{}", gutter, filename, line, column).unwrap();
+        writeln!(out, "{} |", gutter).unwrap();
+        writeln!(out, "{} | {}", line, line_text).unwrap();
+        writeln!(
+            out,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        )
+        .unwrap();
+        if let Some(help) = &self.help {
+            writeln!(out, "{} |", gutter).unwrap();
+            writeln!(out, "{} = help: {}", gutter, help).unwrap();
+        }
+        out
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.phase.exit_code()
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(e: ParseError) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            phase: Phase::Parse,
+            code: e.code,
+            message: format!("expected {}, found {}", e.expected, e.found),
+            span: e.span,
+            help: e.help.map(str::to_string),
+        }
+    }
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(e: TypeError) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            phase: Phase::Type,
+            code: e.code,
+            message: e.message,
+            span: e.span,
+            help: e.help,
+        }
+    }
+}
+
+impl From<EvalError> for Diagnostic {
+    fn from(e: EvalError) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            phase: Phase::Runtime,
+            code: e.code,
+            message: e.message,
+            span: e.span,
+            help: e.help,
+        }
+    }
+}
+
+impl From<Warning> for Diagnostic {
+    fn from(w: Warning) -> Diagnostic {
+        Diagnostic {
+            level: Level::Warning,
+            phase: Phase::Type,
+            code: w.code,
+            message: w.message,
+            span: w.span,
+            help: w.help,
+        }
+    }
+}
+
+#[test]
+fn test_render() {
+    let source = "a := 1;\nb := a + true;\n";
+    let diagnostic = Diagnostic {
+        level: Level::Error,
+        phase: Phase::Type,
+        code: "E0000",
+        message: "something is wrong".to_string(),
+        span: Span {
+            file: 0,
+            start: 13,
+            end: 21,
+        },
+        help: Some("fix it".to_string()),
+    };
+    assert_eq!(
+        diagnostic.render(source, "test.lam"),
+        "error[E0000]: something is wrong\n \
+         --> test.lam:2:6\n  \
+         |\n\
+         2 | b := a + true;\n  \
+         |      ^^^^^^^^\n  \
+         |\n  \
+         = help: fix it\n"
+    );
+}
+
+#[test]
+fn test_render_end_of_input() {
+    let source = "a := 1 +";
+    let diagnostic = Diagnostic {
+        level: Level::Error,
+        phase: Phase::Parse,
+        code: "E0000",
+        message: "unexpected end".to_string(),
+        span: Span {
+            file: 0,
+            start: 8,
+            end: 8,
+        },
+        help: None,
+    };
+    assert_eq!(
+        diagnostic.render(source, "test.lam"),
+        "error[E0000]: unexpected end\n \
+         --> test.lam:1:9\n  \
+         |\n\
+         1 | a := 1 +\n  \
+         |         ^\n"
+    );
+}
+
+#[test]
+fn test_render_unicode() {
+    // `λ` takes two bytes but one column.
+    let source = "x := 1;\nf := λa. a + true;";
+    let diagnostic = Diagnostic {
+        level: Level::Error,
+        phase: Phase::Type,
+        code: "E0000",
+        message: "not a number".to_string(),
+        span: Span {
+            file: 0,
+            start: 22,
+            end: 26,
+        },
+        help: None,
+    };
+    assert_eq!(
+        diagnostic.render(source, "test.lam"),
+        "error[E0000]: not a number\n \
+         --> test.lam:2:14\n  \
+         |\n\
+         2 | f := λa. a + true;\n  \
+         |              ^^^^\n"
+    );
+}
+
+#[test]
+fn test_render_warning() {
+    let source = "x := 1;";
+    let diagnostic: Diagnostic = Warning {
+        code: "W0000",
+        message: "something is odd".to_string(),
+        span: Span {
+            file: 0,
+            start: 5,
+            end: 6,
+        },
+        help: None,
+    }
+    .into();
+    assert_eq!(
+        diagnostic.render(source, "test.lam"),
+        "warning[W0000]: something is odd\n \
+         --> test.lam:1:6\n  \
+         |\n\
+         1 | x := 1;\n  \
+         |      ^\n"
+    );
+}
+
+#[test]
+fn test_exit_codes() {
+    let codes = [
+        Phase::Parse.exit_code(),
+        Phase::Type.exit_code(),
+        Phase::Runtime.exit_code(),
+    ];
+    assert!(codes.iter().all(|&c| c != 0));
+    assert!(codes[0] != codes[1] && codes[1] != codes[2] && codes[0] != codes[2]);
+}
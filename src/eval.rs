@@ -1,96 +1,821 @@
 use ast::*;
-use std::collections::HashMap;
+use builtin::Builtin;
+use num::{self, Num};
+use std::collections::BTreeMap;
+#[cfg(feature = "bigint")]
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
 use value::*;
 
-/// This represents a binding between names and TermTypes.
-pub struct EvalEnv(pub HashMap<String, Value>);
+#[cfg(test)]
+use num::from_i64;
 
-/// Error messages
+/// What arithmetic does when a result does not fit in a number.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Arithmetic {
+    /// Stop the program with a runtime error.
+    #[default]
+    Checked,
+    /// Wrap around, modulo 2^64.
+    Wrapping,
+    /// Clamp to the smallest or the largest number.
+    Saturating,
+}
+
+impl FromStr for Arithmetic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Arithmetic, String> {
+        match s {
+            "checked" => Ok(Arithmetic::Checked),
+            "wrapping" => Ok(Arithmetic::Wrapping),
+            "saturating" => Ok(Arithmetic::Saturating),
+            _ => Err(format!(
+                "unknown arithmetic mode `{}`: expected checked, wrapping or saturating",
+                s
+            )),
+        }
+    }
+}
+
+/// This represents a binding between names and values, along with the
+/// arithmetic the program runs with and the number of calls in progress.
+#[derive(Clone, Debug)]
+pub struct EvalEnv {
+    pub vars: BTreeMap<String, Value>,
+    pub arithmetic: Arithmetic,
+    depth: usize,
+}
+
+/// The most calls that can be in progress at once. Each call takes some of
+/// the evaluator's stack, and running out of it would abort the process.
+const MAX_DEPTH: usize = 100_000;
+
+impl EvalEnv {
+    pub fn new(arithmetic: Arithmetic) -> EvalEnv {
+        EvalEnv {
+            vars: BTreeMap::new(),
+            arithmetic,
+            depth: 0,
+        }
+    }
+
+    /// The environment for the body of a function called at `span`, which
+    /// sees `vars`. Fails if too many calls are already in progress.
+    fn call(&self, vars: BTreeMap<String, Value>, span: Span) -> Result<EvalEnv, EvalError> {
+        if self.depth == MAX_DEPTH {
+            return Err(EvalError {
+                code: E_TOO_DEEP,
+                message: format!("more than {} calls are in progress", MAX_DEPTH),
+                span,
+                help: Some(
+                    "a recursive function may be missing a case that stops it, or may \
+                     recurse more deeply than the interpreter can"
+                        .to_string(),
+                ),
+            });
+        }
+        Ok(EvalEnv {
+            vars,
+            arithmetic: self.arithmetic,
+            depth: self.depth + 1,
+        })
+    }
+}
+
+// Error codes reported for runtime errors. Programs that pass the type
+// checker should never produce an E_ILL_TYPED error.
+pub const E_ILL_TYPED: &str = "E0201";
+pub const E_OVERFLOW: &str = "E0202";
+pub const E_UNDERFLOW: &str = "E0203";
+pub const E_DIVISION_BY_ZERO: &str = "E0204";
+pub const E_NEGATIVE_EXPONENT: &str = "E0205";
+pub const E_EMPTY_LIST: &str = "E0206";
+pub const E_TOO_DEEP: &str = "E0207";
+
+/// The ways arithmetic on well-typed numbers can fail.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ArithmeticError {
+    /// The result is larger than the largest number. Big numbers only
+    /// overflow when a power would have more than `MAX_POWER_BITS` bits.
+    Overflow,
+    /// The result is smaller than the smallest number.
+    #[cfg_attr(feature = "bigint", allow(dead_code))]
+    Underflow,
+    DivisionByZero,
+    NegativeExponent,
+}
+
+impl ArithmeticError {
+    /// Describes the error for the operation written `expr`.
+    fn at(self, expr: &str, span: Span) -> EvalError {
+        let (code, message) = match self {
+            #[cfg(not(feature = "bigint"))]
+            ArithmeticError::Overflow => (
+                E_OVERFLOW,
+                format!("`{}` overflows: numbers are at most {}", expr, i64::MAX),
+            ),
+            #[cfg(feature = "bigint")]
+            ArithmeticError::Overflow => {
+                (E_OVERFLOW, format!("`{}` is too large to compute", expr))
+            }
+            ArithmeticError::Underflow => (
+                E_UNDERFLOW,
+                format!("`{}` underflows: numbers are at least {}", expr, i64::MIN),
+            ),
+            ArithmeticError::DivisionByZero => {
+                (E_DIVISION_BY_ZERO, format!("`{}` divides by zero", expr))
+            }
+            ArithmeticError::NegativeExponent => (
+                E_NEGATIVE_EXPONENT,
+                format!("`{}` has a negative exponent", expr),
+            ),
+        };
+        let help = match self {
+            ArithmeticError::DivisionByZero => "the divisor evaluated to 0".to_string(),
+            ArithmeticError::NegativeExponent => {
+                let help = "the exponent evaluated to a negative number, and only powers of 0 \
+                            or more are integers";
+                help.to_string()
+            }
+            #[cfg(feature = "bigint")]
+            ArithmeticError::Overflow => {
+                format!("a power can have at most {} bits", MAX_POWER_BITS)
+            }
+            _ => "run with `--arithmetic wrapping` or `--arithmetic saturating` to allow this"
+                .to_string(),
+        };
+        EvalError {
+            code,
+            message,
+            span,
+            help: Some(help),
+        }
+    }
+}
+
+/// A runtime error, together with the span of the term that caused it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct EvalError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl Error for EvalError {}
 
-static EVAL_MATH_ERROR: &'static str =
-    "Invalid math operation. Both sides must evaluate to numbers";
-static EVAL_BOOL_ERROR: &'static str =
+// Error messages
+
+static EVAL_MATH_ERROR: &str = "Invalid math operation. Both sides must evaluate to numbers";
+static EVAL_BOOL_ERROR: &str =
     "Both terms in equality must be of the same type. Equality on functions are not supported";
-static EVAL_IF_COND_REQUIRES_BOOL: &'static str = "test condition must be a boolean";
+static EVAL_COMPARE_ERROR: &str = "Both terms in a comparison must evaluate to numbers";
+static EVAL_LOGIC_ERROR: &str = "Logical operators require booleans";
+static EVAL_IF_COND_REQUIRES_BOOL: &str = "test condition must be a boolean";
+static EVAL_PROJECT_ERROR: &str = "Projections require a tuple with that many components";
+static EVAL_FIELD_ERROR: &str = "Field access requires a record with that field";
+static EVAL_CONCAT_ERROR: &str = "Both sides of `++` must evaluate to strings";
+static EVAL_CONS_ERROR: &str = "The tail of `::` must evaluate to a list";
+static EVAL_MATCH_ERROR: &str = "No arm of the match matches the value";
+static EVAL_CASE_ERROR: &str = "case requires a value built by the constructor of one of its arms";
+static EVAL_REC_ERROR: &str = "The functions of a rec group must be lambdas";
 
 /// Main evaluation function. This part of the code assumes that the types are
 /// correct. Although certain patterns would be impossible to reach after type
 /// checking, they are included for completeness... and to satisfy the rust
 /// compiler
-pub fn eval(node: &Term, env: &EvalEnv) -> Result<Value, String> {
-    match node {
-        Term::Var(n) => Ok(env
-            .0
-            .get(n)
-            .ok_or("Variable name missing in environment")?
-            .clone()),
-        Term::Lambda { var_name, expr } => Ok(Value::Closure {
-            env: env.0.clone(),
+///
+/// Each kind of term is evaluated by a function of its own, which keeps the
+/// stack frame of `eval` small: a recursive program nests a call to `eval`
+/// for every step it takes.
+pub fn eval(node: &Term, env: &EvalEnv) -> Result<Value, EvalError> {
+    match &node.kind {
+        TermKind::Var(n) => match (env.vars.get(n), Builtin::named(n)) {
+            (Some(v), _) => Ok(v.clone()),
+            (None, Some(builtin)) => Ok(Value::Builtin {
+                builtin,
+                args: Vec::new(),
+            }),
+            (None, None) => Err(ill_typed("Variable name missing in environment", node.span)),
+        },
+        TermKind::Lambda { var_name, expr, .. } => Ok(Value::Closure {
+            env: Rc::new(env.vars.clone()),
             name: var_name.clone(),
             func_term: expr.clone(),
         }),
-        Term::Apply { var_term, function } => match eval(function, env)? {
-            Value::Closure {
-                env: closure_env,
-                name,
-                func_term,
-            } => {
-                let var_val = eval(var_term, env)?;
-                let mut env_prime = env.0.clone();
-                for (k, v) in &closure_env {
-                    env_prime.insert(k.to_string(), v.clone());
-                }
-                env_prime.insert(name.clone(), var_val);
-                eval(&func_term, &EvalEnv(env_prime))
-            }
-            _ => Err("terms need to be applied to function types".to_string()),
-        },
-        Term::NumConst(n) => Ok(Value::Num(*n)),
-        Term::BoolConst(b) => Ok(Value::Bool(*b)),
-        Term::MathOp { opr, t1, t2 } => eval_bin_math_op(opr, eval(t1, env)?, eval(t2, env)?),
-        Term::Equals {
+        TermKind::Apply { var_term, function } => {
+            eval_apply(eval(function, env)?, var_term, node.span, env)
+        }
+        TermKind::Let { name, value, body } => eval_let(name, value, body, env),
+        TermKind::LetRec { bindings, body } => eval_let_rec(bindings, body, env),
+        TermKind::NumConst(n) => Ok(Value::Num(n.to_owned())),
+        TermKind::BoolConst(b) => Ok(Value::Bool(*b)),
+        TermKind::StrConst(s) => Ok(Value::Str(s.clone())),
+        TermKind::Tuple(components) => eval_all(components, env).map(Value::Tuple),
+        TermKind::List(elements) => eval_all(elements, env).map(Value::List),
+        TermKind::Cons { head, tail } => eval_cons(head, tail, node.span, env),
+        TermKind::Project { tuple, index } => eval_project(tuple, *index, node.span, env),
+        TermKind::Record(fields) => eval_record(fields, env),
+        TermKind::Field { record, field } => eval_field(record, field, node.span, env),
+        TermKind::Negate(t) => eval_negate_term(t, node.span, env),
+        TermKind::Not(t) => eval_not(t, node.span, env),
+        TermKind::MathOp { opr, t1, t2 } => eval_math_op(opr, t1, t2, node.span, env),
+        TermKind::Concat {
+            left_side,
+            right_side,
+        } => eval_concat(left_side, right_side, node.span, env),
+        TermKind::Equals {
             left_side: t1,
             right_side: t2,
-        } => eval_equals(eval(t1, env)?, eval(t2, env)?),
-        Term::NotEquals {
+        } => eval_equals(eval(t1, env)?, eval(t2, env)?).map_err(|e| ill_typed(&e, node.span)),
+        TermKind::NotEquals {
             left_side: t1,
             right_side: t2,
-        } => eval_not_equals(eval(t1, env)?, eval(t2, env)?),
-        Term::IfStmt {
+        } => eval_not_equals(eval(t1, env)?, eval(t2, env)?).map_err(|e| ill_typed(&e, node.span)),
+        TermKind::Compare {
+            opr,
+            left_side,
+            right_side,
+        } => eval_compare(opr, eval(left_side, env)?, eval(right_side, env)?)
+            .map_err(|e| ill_typed(&e, node.span)),
+        TermKind::Logic {
+            opr,
+            left_side,
+            right_side,
+        } => eval_logic(opr, left_side, right_side, node.span, env),
+        TermKind::IfStmt {
             test: c,
             then_body: tb,
             else_body: eb,
-        } => eval_if(&eval(c, env)?, tb, eb, env),
-        Term::Assignm { var_name, expr } => Ok(Value::Assignm {
+        } => match eval(c, env)? {
+            Value::Bool(b) => eval_if(b, tb, eb, env),
+            _ => Err(ill_typed(EVAL_IF_COND_REQUIRES_BOOL, node.span)),
+        },
+        TermKind::Case { scrutinee, arms } => {
+            eval_case(eval(scrutinee, env)?, arms, node.span, env)
+        }
+        TermKind::Match { scrutinee, arms } => {
+            eval_match(&eval(scrutinee, env)?, arms, node.span, env)
+        }
+        TermKind::Assignm { var_name, expr } => Ok(Value::Assignm {
             name: var_name.clone(),
             val: Box::new(eval(expr, env)?),
         }),
+        TermKind::Data { .. } => Err(ill_typed(
+            "data declarations are evaluated with eval_data",
+            node.span,
+        )),
+    }
+}
+
+/// The error for a term that type checking should have rejected.
+fn ill_typed(message: &str, span: Span) -> EvalError {
+    EvalError {
+        code: E_ILL_TYPED,
+        message: message.to_string(),
+        span,
+        help: Some(
+            "the type checker should have rejected this, so this is a bug in the interpreter"
+                .to_string(),
+        ),
+    }
+}
+
+// Applies `function` to the value of `var_term`.
+fn eval_apply(
+    function: Value,
+    var_term: &Term,
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    match function {
+        Value::Closure {
+            env: closure_env,
+            name,
+            func_term,
+        } => {
+            let var_val = eval(var_term, env)?;
+            let mut vars = Rc::try_unwrap(closure_env).unwrap_or_else(|env| (*env).clone());
+            vars.insert(name, var_val);
+            eval(&func_term, &env.call(vars, span)?)
+        }
+        Value::Rec {
+            env: rec_env,
+            group,
+            name,
+        } => {
+            let var_val = eval(var_term, env)?;
+            match group.iter().find(|(n, _)| *n == name) {
+                Some((_, function)) => match &function.kind {
+                    TermKind::Lambda { var_name, expr, .. } => {
+                        let mut vars = rec_group_vars(&rec_env, &group);
+                        vars.insert(var_name.clone(), var_val);
+                        eval(expr, &env.call(vars, span)?)
+                    }
+                    _ => Err(ill_typed(EVAL_REC_ERROR, span)),
+                },
+                None => Err(ill_typed(EVAL_REC_ERROR, span)),
+            }
+        }
+        Value::Builtin { builtin, mut args } => {
+            args.push(eval(var_term, env)?);
+            if args.len() < builtin.arity() {
+                Ok(Value::Builtin { builtin, args })
+            } else {
+                eval_builtin(builtin, &args, env.arithmetic, span)
+            }
+        }
+        Value::Constructor {
+            name,
+            arity,
+            mut args,
+        } => {
+            args.push(eval(var_term, env)?);
+            if args.len() < arity {
+                Ok(Value::Constructor { name, arity, args })
+            } else {
+                Ok(Value::Tagged {
+                    constructor: name,
+                    fields: args,
+                })
+            }
+        }
+        _ => Err(ill_typed(
+            "terms need to be applied to function types",
+            span,
+        )),
+    }
+}
+
+fn eval_let(name: &str, value: &Term, body: &Term, env: &EvalEnv) -> Result<Value, EvalError> {
+    let mut body_env = env.clone();
+    body_env.vars.insert(name.to_string(), eval(value, env)?);
+    eval(body, &body_env)
+}
+
+fn eval_let_rec(
+    bindings: &[(String, Term)],
+    body: &Term,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    let mut body_env = env.clone();
+    body_env.vars.extend(eval_rec_group(bindings, env));
+    eval(body, &body_env)
+}
+
+fn eval_all(terms: &[Term], env: &EvalEnv) -> Result<Vec<Value>, EvalError> {
+    terms.iter().map(|term| eval(term, env)).collect()
+}
+
+fn eval_cons(head: &Term, tail: &Term, span: Span, env: &EvalEnv) -> Result<Value, EvalError> {
+    let head = eval(head, env)?;
+    match eval(tail, env)? {
+        Value::List(mut values) => {
+            values.insert(0, head);
+            Ok(Value::List(values))
+        }
+        _ => Err(ill_typed(EVAL_CONS_ERROR, span)),
+    }
+}
+
+fn eval_project(tuple: &Term, index: usize, span: Span, env: &EvalEnv) -> Result<Value, EvalError> {
+    match eval(tuple, env)? {
+        Value::Tuple(mut values) if index < values.len() => Ok(values.swap_remove(index)),
+        _ => Err(ill_typed(EVAL_PROJECT_ERROR, span)),
+    }
+}
+
+fn eval_record(fields: &[(String, Term)], env: &EvalEnv) -> Result<Value, EvalError> {
+    let mut values = BTreeMap::new();
+    for (name, value) in fields {
+        values.insert(name.clone(), eval(value, env)?);
+    }
+    Ok(Value::Record(values))
+}
+
+fn eval_field(record: &Term, field: &str, span: Span, env: &EvalEnv) -> Result<Value, EvalError> {
+    match eval(record, env)? {
+        Value::Record(mut values) => values
+            .remove(field)
+            .ok_or_else(|| ill_typed(EVAL_FIELD_ERROR, span)),
+        _ => Err(ill_typed(EVAL_FIELD_ERROR, span)),
+    }
+}
+
+fn eval_negate_term(t: &Term, span: Span, env: &EvalEnv) -> Result<Value, EvalError> {
+    match eval(t, env)? {
+        Value::Num(v) => eval_negate(&v, env.arithmetic)
+            .map(Value::Num)
+            .map_err(|e| e.at(&format!("-({})", v), span)),
+        _ => Err(ill_typed(EVAL_MATH_ERROR, span)),
+    }
+}
+
+fn eval_not(t: &Term, span: Span, env: &EvalEnv) -> Result<Value, EvalError> {
+    match eval(t, env)? {
+        Value::Bool(b) => Ok(Value::Bool(!b)),
+        _ => Err(ill_typed(EVAL_LOGIC_ERROR, span)),
+    }
+}
+
+fn eval_math_op(
+    opr: &BinMathOp,
+    t1: &Term,
+    t2: &Term,
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    match (eval(t1, env)?, eval(t2, env)?) {
+        (Value::Num(v1), Value::Num(v2)) => eval_bin_math_op(opr, &v1, &v2, env.arithmetic)
+            .map(Value::Num)
+            .map_err(|e| e.at(&format!("{} {} {}", v1, opr, v2), span)),
+        _ => Err(ill_typed(EVAL_MATH_ERROR, span)),
+    }
+}
+
+fn eval_concat(
+    left_side: &Term,
+    right_side: &Term,
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    match (eval(left_side, env)?, eval(right_side, env)?) {
+        (Value::Str(s1), Value::Str(s2)) => Ok(Value::Str(s1 + &s2)),
+        _ => Err(ill_typed(EVAL_CONCAT_ERROR, span)),
+    }
+}
+
+// Evaluates `&&` and `||`, which only evaluate their right side when the left
+// side does not decide the result.
+fn eval_logic(
+    opr: &LogicOp,
+    left_side: &Term,
+    right_side: &Term,
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    match (opr, eval(left_side, env)?) {
+        (LogicOp::And, Value::Bool(false)) => Ok(Value::Bool(false)),
+        (LogicOp::Or, Value::Bool(true)) => Ok(Value::Bool(true)),
+        (_, Value::Bool(_)) => match eval(right_side, env)? {
+            Value::Bool(b) => Ok(Value::Bool(b)),
+            _ => Err(ill_typed(EVAL_LOGIC_ERROR, span)),
+        },
+        _ => Err(ill_typed(EVAL_LOGIC_ERROR, span)),
+    }
+}
+
+fn eval_case(
+    scrutinee: Value,
+    arms: &[CaseArm],
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    match scrutinee {
+        Value::Tagged {
+            constructor,
+            fields,
+        } => match arms.iter().find(|arm| arm.constructor == constructor) {
+            Some(arm) if arm.vars.len() == fields.len() => {
+                let mut arm_env = env.clone();
+                arm_env.vars.extend(arm.vars.iter().cloned().zip(fields));
+                eval(&arm.body, &arm_env)
+            }
+            _ => Err(ill_typed(EVAL_CASE_ERROR, span)),
+        },
+        _ => Err(ill_typed(EVAL_CASE_ERROR, span)),
+    }
+}
+
+fn eval_match(
+    value: &Value,
+    arms: &[MatchArm],
+    span: Span,
+    env: &EvalEnv,
+) -> Result<Value, EvalError> {
+    for arm in arms {
+        let mut arm_env = env.clone();
+        if match_pattern(&arm.pattern, value, &mut arm_env.vars) {
+            return eval(&arm.body, &arm_env);
+        }
+    }
+    Err(ill_typed(EVAL_MATCH_ERROR, span))
+}
+
+/// Whether `value` matches `pattern`, binding the names of the pattern in
+/// `vars` if it does.
+fn match_pattern(pattern: &Pattern, value: &Value, vars: &mut BTreeMap<String, Value>) -> bool {
+    match (pattern, value) {
+        (Pattern::Num(n), Value::Num(v)) => n == v,
+        (Pattern::Bool(b), Value::Bool(v)) => b == v,
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Var(name), _) => {
+            vars.insert(name.clone(), value.clone());
+            true
+        }
+        (Pattern::Nil, Value::List(values)) => values.is_empty(),
+        (Pattern::Cons(head, tail), Value::List(values)) => match values.split_first() {
+            Some((first, rest)) => {
+                match_pattern(head, first, vars)
+                    && match_pattern(tail, &Value::List(rest.to_vec()), vars)
+            }
+            None => false,
+        },
+        (_, _) => false,
+    }
+}
+
+/// The values of the constructors of a data declaration: a constructor with
+/// no fields is a value of the data type, and any other is a function that
+/// takes its fields one at a time.
+pub fn eval_data(constructors: &[(String, Vec<TypeAnn>)]) -> Vec<(String, Value)> {
+    constructors
+        .iter()
+        .map(|(name, fields)| {
+            let value = if fields.is_empty() {
+                Value::Tagged {
+                    constructor: name.clone(),
+                    fields: Vec::new(),
+                }
+            } else {
+                Value::Constructor {
+                    name: name.clone(),
+                    arity: fields.len(),
+                    args: Vec::new(),
+                }
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// The functions of a group of definitions that may all refer to each
+/// other, defined in `env`.
+pub fn eval_rec_group(bindings: &[(String, Term)], env: &EvalEnv) -> Vec<(String, Value)> {
+    rec_group_values(&Rc::new(env.vars.clone()), &bindings.into())
+}
+
+/// The functions of `group`, which all share `env` and `group`.
+fn rec_group_values(
+    env: &Rc<BTreeMap<String, Value>>,
+    group: &Rc<[(String, Term)]>,
+) -> Vec<(String, Value)> {
+    group
+        .iter()
+        .map(|(name, _)| {
+            let value = Value::Rec {
+                env: Rc::clone(env),
+                group: Rc::clone(group),
+                name: name.clone(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// What the body of a function of `group` sees: `env`, and the functions
+/// of the group.
+fn rec_group_vars(
+    env: &Rc<BTreeMap<String, Value>>,
+    group: &Rc<[(String, Term)]>,
+) -> BTreeMap<String, Value> {
+    let mut vars = (**env).clone();
+    vars.extend(rec_group_values(env, group));
+    vars
+}
+
+#[cfg(not(feature = "bigint"))]
+fn eval_negate(v: &Num, arithmetic: Arithmetic) -> Result<Num, ArithmeticError> {
+    match arithmetic {
+        Arithmetic::Checked => v.checked_neg().ok_or(ArithmeticError::Overflow),
+        Arithmetic::Wrapping => Ok(v.wrapping_neg()),
+        Arithmetic::Saturating => Ok(v.saturating_neg()),
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn eval_bin_math_op(
+    opr: &BinMathOp,
+    v1: &Num,
+    v2: &Num,
+    arithmetic: Arithmetic,
+) -> Result<Num, ArithmeticError> {
+    let (v1, v2) = (*v1, *v2);
+    match (opr, v2) {
+        (BinMathOp::Divide, 0) | (BinMathOp::Modulo, 0) => {
+            return Err(ArithmeticError::DivisionByZero)
+        }
+        // The remainder is always smaller than the divisor, so it fits even
+        // when the quotient does not.
+        (BinMathOp::Modulo, _) => return Ok(v1.wrapping_rem(v2)),
+        (BinMathOp::Power, _) => return eval_power(v1, v2, arithmetic),
+        _ => {}
+    }
+    // Should the result not fit, whether it is too large or too small.
+    let too_large = match opr {
+        BinMathOp::Add => v2 > 0,
+        BinMathOp::Minus => v2 < 0,
+        _ => (v1 < 0) == (v2 < 0),
+    };
+    let out_of_range = if too_large {
+        ArithmeticError::Overflow
+    } else {
+        ArithmeticError::Underflow
+    };
+    match (opr, arithmetic) {
+        (BinMathOp::Add, Arithmetic::Checked) => v1.checked_add(v2).ok_or(out_of_range),
+        (BinMathOp::Minus, Arithmetic::Checked) => v1.checked_sub(v2).ok_or(out_of_range),
+        (BinMathOp::Multiply, Arithmetic::Checked) => v1.checked_mul(v2).ok_or(out_of_range),
+        (BinMathOp::Divide, Arithmetic::Checked) => v1.checked_div(v2).ok_or(out_of_range),
+        (BinMathOp::Add, Arithmetic::Wrapping) => Ok(v1.wrapping_add(v2)),
+        (BinMathOp::Minus, Arithmetic::Wrapping) => Ok(v1.wrapping_sub(v2)),
+        (BinMathOp::Multiply, Arithmetic::Wrapping) => Ok(v1.wrapping_mul(v2)),
+        (BinMathOp::Divide, Arithmetic::Wrapping) => Ok(v1.wrapping_div(v2)),
+        (BinMathOp::Add, Arithmetic::Saturating) => Ok(v1.saturating_add(v2)),
+        (BinMathOp::Minus, Arithmetic::Saturating) => Ok(v1.saturating_sub(v2)),
+        (BinMathOp::Multiply, Arithmetic::Saturating) => Ok(v1.saturating_mul(v2)),
+        (BinMathOp::Divide, Arithmetic::Saturating) => Ok(v1.saturating_div(v2)),
+        (BinMathOp::Modulo, _) | (BinMathOp::Power, _) => unreachable!(),
     }
 }
 
-fn eval_bin_math_op(opr: &BinMathOp, t1: Value, t2: Value) -> Result<Value, String> {
-    match (opr, t1, t2) {
-        (BinMathOp::Add, Value::Num(v1), Value::Num(v2)) => Ok(Value::Num(v1 + v2)),
-        (BinMathOp::Minus, Value::Num(v1), Value::Num(v2)) => Ok(Value::Num(v1 - v2)),
-        (BinMathOp::Multiply, Value::Num(v1), Value::Num(v2)) => Ok(Value::Num(v1 * v2)),
-        (BinMathOp::Divide, Value::Num(v1), Value::Num(v2)) => Ok(Value::Num(v1 / v2)),
-        _ => Err(EVAL_MATH_ERROR.to_string()),
+/// Raises `base` to `exponent` by repeated squaring, multiplying with the
+/// given arithmetic.
+#[cfg(not(feature = "bigint"))]
+fn eval_power(base: Num, exponent: Num, arithmetic: Arithmetic) -> Result<Num, ArithmeticError> {
+    if exponent < 0 {
+        return Err(ArithmeticError::NegativeExponent);
+    }
+    let out_of_range = if base >= 0 || exponent % 2 == 0 {
+        ArithmeticError::Overflow
+    } else {
+        ArithmeticError::Underflow
+    };
+    let multiply = |v1, v2| {
+        eval_bin_math_op(&BinMathOp::Multiply, &v1, &v2, arithmetic).map_err(|_| out_of_range)
+    };
+    let (mut base, mut exponent, mut result) = (base, exponent, 1);
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = multiply(result, base)?;
+        }
+        exponent /= 2;
+        // Only square when the square is needed: it may not fit even though
+        // the result does.
+        if exponent > 0 {
+            base = multiply(base, base)?;
+        }
     }
+    Ok(result)
+}
+
+// Big numbers never overflow, so every arithmetic mode agrees.
+#[cfg(feature = "bigint")]
+fn eval_negate(v: &Num, _arithmetic: Arithmetic) -> Result<Num, ArithmeticError> {
+    Ok(-v)
+}
+
+#[cfg(feature = "bigint")]
+fn eval_bin_math_op(
+    opr: &BinMathOp,
+    v1: &Num,
+    v2: &Num,
+    _arithmetic: Arithmetic,
+) -> Result<Num, ArithmeticError> {
+    match opr {
+        BinMathOp::Add => Ok(v1 + v2),
+        BinMathOp::Minus => Ok(v1 - v2),
+        BinMathOp::Multiply => Ok(v1 * v2),
+        BinMathOp::Divide if *v2 == Num::default() => Err(ArithmeticError::DivisionByZero),
+        BinMathOp::Divide => Ok(v1 / v2),
+        BinMathOp::Modulo if *v2 == Num::default() => Err(ArithmeticError::DivisionByZero),
+        BinMathOp::Modulo => Ok(v1 % v2),
+        BinMathOp::Power if *v2 < Num::default() => Err(ArithmeticError::NegativeExponent),
+        BinMathOp::Power => match u32::try_from(v2) {
+            Ok(exponent) if power_fits(v1, exponent) => Ok(v1.pow(exponent)),
+            // Only 0, 1 and -1 have powers this large that fit in memory.
+            Err(_) if v1.magnitude() <= &1u32.into() => {
+                let odd = v2 % 2 != Num::default();
+                Ok(if odd { v1.clone() } else { v1 * v1 })
+            }
+            _ => Err(ArithmeticError::Overflow),
+        },
+    }
+}
+
+/// The most bits a power can have. Powers are only computed when they are
+/// known to be smaller, since a larger one could take too long to compute.
+#[cfg(feature = "bigint")]
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+/// Whether `base ^ exponent` has at most `MAX_POWER_BITS` bits, judged by a
+/// lower bound on its size, before computing it.
+#[cfg(feature = "bigint")]
+fn power_fits(base: &Num, exponent: u32) -> bool {
+    base.bits().saturating_sub(1) * u64::from(exponent) <= MAX_POWER_BITS
+}
+
+fn eval_builtin(
+    builtin: Builtin,
+    args: &[Value],
+    arithmetic: Arithmetic,
+    span: Span,
+) -> Result<Value, EvalError> {
+    let result = match (builtin, args) {
+        (Builtin::Min, [Value::Num(v1), Value::Num(v2)]) => Ok(v1.min(v2).to_owned()),
+        (Builtin::Max, [Value::Num(v1), Value::Num(v2)]) => Ok(v1.max(v2).to_owned()),
+        (Builtin::Abs, [Value::Num(v)]) if *v < Num::default() => eval_negate(v, arithmetic),
+        (Builtin::Abs, [Value::Num(v)]) => Ok(v.to_owned()),
+        (Builtin::Fst, [Value::Tuple(values)]) => return Ok(values[0].clone()),
+        (Builtin::Snd, [Value::Tuple(values)]) => return Ok(values[1].clone()),
+        (Builtin::Head, [Value::List(values)]) | (Builtin::Tail, [Value::List(values)])
+            if values.is_empty() =>
+        {
+            return Err(EvalError {
+                code: E_EMPTY_LIST,
+                message: format!("`{}` of an empty list", builtin.name()),
+                span,
+                help: Some(
+                    "check with `isEmpty` first, or `match` the list with a `[]` arm".to_string(),
+                ),
+            })
+        }
+        (Builtin::Head, [Value::List(values)]) => return Ok(values[0].clone()),
+        (Builtin::Tail, [Value::List(values)]) => return Ok(Value::List(values[1..].to_vec())),
+        (Builtin::IsEmpty, [Value::List(values)]) => return Ok(Value::Bool(values.is_empty())),
+        (Builtin::Length, [Value::List(values)]) => Ok(num::from_usize(values.len())),
+        (Builtin::Length, [Value::Str(s)]) => Ok(num::from_usize(s.chars().count())),
+        (Builtin::Show, [Value::Num(v)]) => return Ok(Value::Str(v.to_string())),
+        (Builtin::Show, [Value::Bool(b)]) => return Ok(Value::Str(b.to_string())),
+        _ => return Err(ill_typed(EVAL_MATH_ERROR, span)),
+    };
+    result.map(Value::Num).map_err(|e| {
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| match arg {
+                Value::Num(v) => v.to_string(),
+                _ => unreachable!(),
+            })
+            .collect();
+        e.at(&format!("{} {}", builtin.name(), args.join(" ")), span)
+    })
 }
 
 fn eval_equals(t1: Value, t2: Value) -> Result<Value, String> {
+    values_equal(&t1, &t2).map(Value::Bool)
+}
+
+fn eval_not_equals(t1: Value, t2: Value) -> Result<Value, String> {
+    values_equal(&t1, &t2).map(|equal| Value::Bool(!equal))
+}
+
+/// Compares two values structurally: tuples, records and lists are equal
+/// when their components are.
+fn values_equal(t1: &Value, t2: &Value) -> Result<bool, String> {
+    let all_equal = |pairs: &mut dyn Iterator<Item = (&Value, &Value)>| {
+        for (v1, v2) in pairs {
+            if !values_equal(v1, v2)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    };
     match (t1, t2) {
-        (Value::Num(num1), Value::Num(num2)) => Ok(Value::Bool(num1 == num2)),
-        (Value::Bool(bool1), Value::Bool(bool2)) => Ok(Value::Bool(bool1 == bool2)),
+        (Value::Num(num1), Value::Num(num2)) => Ok(num1 == num2),
+        (Value::Bool(bool1), Value::Bool(bool2)) => Ok(bool1 == bool2),
+        (Value::Str(s1), Value::Str(s2)) => Ok(s1 == s2),
+        (Value::Tuple(values1), Value::Tuple(values2)) if values1.len() == values2.len() => {
+            all_equal(&mut values1.iter().zip(values2))
+        }
+        (Value::Record(fields1), Value::Record(fields2)) if fields1.keys().eq(fields2.keys()) => {
+            all_equal(&mut fields1.values().zip(fields2.values()))
+        }
+        (Value::List(values1), Value::List(values2)) => {
+            Ok(values1.len() == values2.len() && all_equal(&mut values1.iter().zip(values2))?)
+        }
         (_, _) => Err(EVAL_BOOL_ERROR.to_string()),
     }
 }
 
-fn eval_not_equals(t1: Value, t2: Value) -> Result<Value, String> {
+fn eval_compare(opr: &CompareOp, t1: Value, t2: Value) -> Result<Value, String> {
     match (t1, t2) {
-        (Value::Num(num1), Value::Num(num2)) => Ok(Value::Bool(num1 != num2)),
-        (Value::Bool(bool1), Value::Bool(bool2)) => Ok(Value::Bool(bool1 != bool2)),
-        (_, _) => Err(EVAL_BOOL_ERROR.to_string()),
+        (Value::Num(num1), Value::Num(num2)) => Ok(Value::Bool(match opr {
+            CompareOp::Less => num1 < num2,
+            CompareOp::LessEq => num1 <= num2,
+            CompareOp::Greater => num1 > num2,
+            CompareOp::GreaterEq => num1 >= num2,
+        })),
+        (_, _) => Err(EVAL_COMPARE_ERROR.to_string()),
     }
 }
 
@@ -98,122 +823,582 @@ fn eval_not_equals(t1: Value, t2: Value) -> Result<Value, String> {
 // Note: at this point, type checking should have ensured that both branches of the condition
 // have the same type.
 fn eval_if(
-    test: &Value,
+    test: bool,
     then_body: &Term,
     else_body: &Term,
     env: &EvalEnv,
-) -> Result<Value, String> {
-    match test {
-        Value::Bool(true) => eval(then_body, env),
-        Value::Bool(false) => eval(else_body, env),
-        _ => Err(EVAL_IF_COND_REQUIRES_BOOL.to_string()),
+) -> Result<Value, EvalError> {
+    if test {
+        eval(then_body, env)
+    } else {
+        eval(else_body, env)
     }
 }
 
 #[test]
 fn test_ev_const_vals() {
-    let ast_num = Term::NumConst(1);
-    let env = EvalEnv(HashMap::new());
-    assert_eq!(Ok(Value::Num(1)), eval(&ast_num, &env));
+    let ast_num: Term = TermKind::NumConst(from_i64(1)).into();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(Ok(Value::Num(from_i64(1))), eval(&ast_num, &env));
 
-    let ast_bool = Term::BoolConst(true);
+    let ast_bool: Term = TermKind::BoolConst(true).into();
     assert_eq!(Ok(Value::Bool(true)), eval(&ast_bool, &env));
 }
 
 #[test]
 fn test_mathops() {
-    let add_expr = Term::MathOp {
+    let add_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Add,
-        t1: Box::new(Term::NumConst(4)),
-        t2: Box::new(Term::NumConst(6)),
-    };
-    let sub_expr = Term::MathOp {
+        t1: Box::new(TermKind::NumConst(from_i64(4)).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
+    let sub_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Minus,
-        t1: Box::new(Term::NumConst(6)),
-        t2: Box::new(Term::NumConst(4)),
-    };
-    let mul_expr = Term::MathOp {
+        t1: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(4)).into()),
+    }
+    .into();
+    let mul_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Multiply,
-        t1: Box::new(Term::NumConst(6)),
-        t2: Box::new(Term::NumConst(4)),
-    };
-    let div_expr = Term::MathOp {
+        t1: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(4)).into()),
+    }
+    .into();
+    let div_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Divide,
-        t1: Box::new(Term::NumConst(6)),
-        t2: Box::new(Term::NumConst(3)),
-    };
-    let incorrect_1 = Term::MathOp {
+        t1: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(3)).into()),
+    }
+    .into();
+    let incorrect_1: Term = TermKind::MathOp {
         opr: BinMathOp::Divide,
-        t1: Box::new(Term::BoolConst(true)),
-        t2: Box::new(Term::NumConst(3)),
-    };
-    let incorrect_2 = Term::MathOp {
+        t1: Box::new(TermKind::BoolConst(true).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(3)).into()),
+    }
+    .into();
+    let incorrect_2: Term = TermKind::MathOp {
         opr: BinMathOp::Divide,
-        t1: Box::new(Term::NumConst(3)),
-        t2: Box::new(Term::BoolConst(true)),
-    };
+        t1: Box::new(TermKind::NumConst(from_i64(3)).into()),
+        t2: Box::new(TermKind::BoolConst(true).into()),
+    }
+    .into();
 
-    let env = EvalEnv(HashMap::new());
-    assert_eq!(Ok(Value::Num(2)), eval(&sub_expr, &env));
-    assert_eq!(Ok(Value::Num(10)), eval(&add_expr, &env));
-    assert_eq!(Ok(Value::Num(24)), eval(&mul_expr, &env));
-    assert_eq!(Ok(Value::Num(2)), eval(&div_expr, &env));
-    assert_eq!(Err(EVAL_MATH_ERROR.to_string()), eval(&incorrect_1, &env));
-    assert_eq!(Err(EVAL_MATH_ERROR.to_string()), eval(&incorrect_2, &env));
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(Ok(Value::Num(from_i64(2))), eval(&sub_expr, &env));
+    assert_eq!(Ok(Value::Num(from_i64(10))), eval(&add_expr, &env));
+    assert_eq!(Ok(Value::Num(from_i64(24))), eval(&mul_expr, &env));
+    assert_eq!(Ok(Value::Num(from_i64(2))), eval(&div_expr, &env));
+    assert_eq!(
+        Err(EVAL_MATH_ERROR.to_string()),
+        eval(&incorrect_1, &env).map_err(|e| e.message)
+    );
+    assert_eq!(
+        Err(EVAL_MATH_ERROR.to_string()),
+        eval(&incorrect_2, &env).map_err(|e| e.message)
+    );
 }
 
 #[test]
 fn test_boolops() {
-    let eq_expr_1 = Term::Equals {
-        left_side: Box::new(Term::NumConst(4)),
-        right_side: Box::new(Term::NumConst(6)),
-    };
-    let eq_expr_2 = Term::Equals {
-        left_side: Box::new(Term::NumConst(6)),
-        right_side: Box::new(Term::NumConst(6)),
-    };
-    let eq_expr_3 = Term::NotEquals {
-        left_side: Box::new(Term::BoolConst(false)),
-        right_side: Box::new(Term::BoolConst(false)),
-    };
-    let eq_expr_4 = Term::NotEquals {
-        left_side: Box::new(Term::BoolConst(true)),
-        right_side: Box::new(Term::BoolConst(false)),
-    };
-    let eq_expr_5 = Term::NotEquals {
-        left_side: Box::new(Term::NumConst(1)),
-        right_side: Box::new(Term::BoolConst(false)),
-    };
-    let env = EvalEnv(HashMap::new());
+    let eq_expr_1: Term = TermKind::Equals {
+        left_side: Box::new(TermKind::NumConst(from_i64(4)).into()),
+        right_side: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
+    let eq_expr_2: Term = TermKind::Equals {
+        left_side: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        right_side: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
+    let eq_expr_3: Term = TermKind::NotEquals {
+        left_side: Box::new(TermKind::BoolConst(false).into()),
+        right_side: Box::new(TermKind::BoolConst(false).into()),
+    }
+    .into();
+    let eq_expr_4: Term = TermKind::NotEquals {
+        left_side: Box::new(TermKind::BoolConst(true).into()),
+        right_side: Box::new(TermKind::BoolConst(false).into()),
+    }
+    .into();
+    let eq_expr_5: Term = TermKind::NotEquals {
+        left_side: Box::new(TermKind::NumConst(from_i64(1)).into()),
+        right_side: Box::new(TermKind::BoolConst(false).into()),
+    }
+    .into();
+    let env = EvalEnv::new(Arithmetic::Checked);
     assert_eq!(Ok(Value::Bool(false)), eval(&eq_expr_1, &env));
     assert_eq!(Ok(Value::Bool(true)), eval(&eq_expr_2, &env));
     assert_eq!(Ok(Value::Bool(false)), eval(&eq_expr_3, &env));
     assert_eq!(Ok(Value::Bool(true)), eval(&eq_expr_4, &env));
-    assert_eq!(Err(EVAL_BOOL_ERROR.to_string()), eval(&eq_expr_5, &env));
+    assert_eq!(
+        Err(EVAL_BOOL_ERROR.to_string()),
+        eval(&eq_expr_5, &env).map_err(|e| e.message)
+    );
+}
+
+#[test]
+fn test_compare() {
+    let compare = |opr, left, right| -> Term {
+        TermKind::Compare {
+            opr,
+            left_side: Box::new(TermKind::NumConst(from_i64(left)).into()),
+            right_side: Box::new(TermKind::NumConst(from_i64(right)).into()),
+        }
+        .into()
+    };
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        eval(&compare(CompareOp::Less, -1, 0), &env)
+    );
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        eval(&compare(CompareOp::Less, 0, 0), &env)
+    );
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        eval(&compare(CompareOp::LessEq, 0, 0), &env)
+    );
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        eval(&compare(CompareOp::Greater, 2, 7), &env)
+    );
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        eval(&compare(CompareOp::GreaterEq, 7, 2), &env)
+    );
+}
+
+#[test]
+fn test_short_circuit() {
+    let fails: Term = TermKind::Var("undefined".to_string()).into();
+    let logic = |opr, left| -> Term {
+        TermKind::Logic {
+            opr,
+            left_side: Box::new(TermKind::BoolConst(left).into()),
+            right_side: Box::new(fails.clone()),
+        }
+        .into()
+    };
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        eval(&logic(LogicOp::And, false), &env)
+    );
+    assert_eq!(Ok(Value::Bool(true)), eval(&logic(LogicOp::Or, true), &env));
+    assert!(eval(&logic(LogicOp::And, true), &env).is_err());
+    assert!(eval(&logic(LogicOp::Or, false), &env).is_err());
 }
 
 #[test]
 fn test_if() {
-    let if_1 = Term::IfStmt {
-        test: Box::new(Term::BoolConst(true)),
-        then_body: Box::new(Term::NumConst(6)),
-        else_body: Box::new(Term::NumConst(7)),
+    let if_1: Term = TermKind::IfStmt {
+        test: Box::new(TermKind::BoolConst(true).into()),
+        then_body: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        else_body: Box::new(TermKind::NumConst(from_i64(7)).into()),
+    }
+    .into();
+    let if_2: Term = TermKind::IfStmt {
+        test: Box::new(TermKind::NumConst(from_i64(1)).into()),
+        then_body: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        else_body: Box::new(TermKind::NumConst(from_i64(7)).into()),
+    }
+    .into();
+    let if_3: Term = TermKind::IfStmt {
+        test: Box::new(TermKind::BoolConst(false).into()),
+        then_body: Box::new(TermKind::NumConst(from_i64(6)).into()),
+        else_body: Box::new(TermKind::NumConst(from_i64(7)).into()),
+    }
+    .into();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(Ok(Value::Num(from_i64(6))), eval(&if_1, &env));
+    assert_eq!(
+        Err(EVAL_IF_COND_REQUIRES_BOOL.to_string()),
+        eval(&if_2, &env).map_err(|e| e.message)
+    );
+    assert_eq!(Ok(Value::Num(from_i64(7))), eval(&if_3, &env));
+}
+
+#[test]
+fn test_let() {
+    let var = |name: &str| -> Box<Term> { Box::new(TermKind::Var(name.to_string()).into()) };
+    // let x = 2 in (let x = x * x in x) + x
+    let inner: Term = TermKind::Let {
+        name: "x".to_string(),
+        value: Box::new(
+            TermKind::MathOp {
+                opr: BinMathOp::Multiply,
+                t1: var("x"),
+                t2: var("x"),
+            }
+            .into(),
+        ),
+        body: var("x"),
+    }
+    .into();
+    let outer: Term = TermKind::Let {
+        name: "x".to_string(),
+        value: Box::new(TermKind::NumConst(from_i64(2)).into()),
+        body: Box::new(
+            TermKind::MathOp {
+                opr: BinMathOp::Add,
+                t1: Box::new(inner),
+                t2: var("x"),
+            }
+            .into(),
+        ),
+    }
+    .into();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(Ok(Value::Num(from_i64(6))), eval(&outer, &env));
+    assert!(env.vars.is_empty());
+}
+
+#[test]
+#[cfg(not(feature = "bigint"))]
+fn test_arithmetic_modes() {
+    use self::Arithmetic::*;
+    use self::ArithmeticError::*;
+    use ast::BinMathOp::*;
+
+    let (min, max) = (i64::MIN, i64::MAX);
+    assert_eq!(Ok(-1), eval_bin_math_op(&Minus, &1, &2, Checked));
+    assert_eq!(Err(Overflow), eval_bin_math_op(&Add, &max, &1, Checked));
+    assert_eq!(Err(Underflow), eval_bin_math_op(&Minus, &min, &1, Checked));
+    assert_eq!(Err(Overflow), eval_bin_math_op(&Minus, &max, &-1, Checked));
+    assert_eq!(
+        Err(Underflow),
+        eval_bin_math_op(&Multiply, &max, &-2, Checked)
+    );
+    assert_eq!(Err(Overflow), eval_bin_math_op(&Divide, &min, &-1, Checked));
+    assert_eq!(Err(Overflow), eval_negate(&min, Checked));
+    assert_eq!(Ok(min), eval_bin_math_op(&Add, &max, &1, Wrapping));
+    assert_eq!(Ok(min), eval_negate(&min, Wrapping));
+    assert_eq!(Ok(min), eval_bin_math_op(&Minus, &min, &1, Saturating));
+    assert_eq!(Ok(max), eval_bin_math_op(&Divide, &min, &-1, Saturating));
+    assert_eq!(Ok(max), eval_negate(&min, Saturating));
+    for &mode in &[Checked, Wrapping, Saturating] {
+        assert_eq!(Err(DivisionByZero), eval_bin_math_op(&Divide, &5, &0, mode));
+        assert_eq!(Ok(-2), eval_bin_math_op(&Divide, &-7, &3, mode));
+    }
+}
+
+#[test]
+#[cfg(not(feature = "bigint"))]
+fn test_modulo_and_power() {
+    use self::Arithmetic::*;
+    use self::ArithmeticError::*;
+    use ast::BinMathOp::*;
+
+    let (min, max) = (i64::MIN, i64::MAX);
+    assert_eq!(Ok(-1), eval_bin_math_op(&Modulo, &-7, &3, Checked));
+    assert_eq!(Ok(0), eval_bin_math_op(&Modulo, &min, &-1, Checked));
+    assert_eq!(Ok(1024), eval_bin_math_op(&Power, &2, &10, Checked));
+    assert_eq!(Ok(min), eval_bin_math_op(&Power, &-2, &63, Checked));
+    assert_eq!(Err(Overflow), eval_bin_math_op(&Power, &2, &63, Checked));
+    assert_eq!(Err(Underflow), eval_bin_math_op(&Power, &-3, &41, Checked));
+    assert_eq!(Ok(-1), eval_bin_math_op(&Power, &-1, &max, Checked));
+    assert_eq!(Ok(0), eval_bin_math_op(&Power, &2, &64, Wrapping));
+    assert_eq!(Ok(max), eval_bin_math_op(&Power, &10, &100, Saturating));
+    assert_eq!(Ok(min), eval_bin_math_op(&Power, &-10, &101, Saturating));
+    for &mode in &[Checked, Wrapping, Saturating] {
+        assert_eq!(Err(DivisionByZero), eval_bin_math_op(&Modulo, &5, &0, mode));
+        assert_eq!(
+            Err(NegativeExponent),
+            eval_bin_math_op(&Power, &2, &-1, mode)
+        );
+        assert_eq!(Ok(1), eval_bin_math_op(&Power, &0, &0, mode));
+    }
+}
+
+#[test]
+#[cfg(feature = "bigint")]
+fn test_big_arithmetic() {
+    use self::Arithmetic::*;
+    use ast::BinMathOp::*;
+
+    let big = from_i64(i64::MIN);
+    let product = eval_bin_math_op(&Multiply, &big, &big, Checked).unwrap();
+    assert_eq!(
+        "85070591730234615865843651857942052864",
+        product.to_string()
+    );
+    assert_eq!(
+        Ok(-(&product)),
+        eval_bin_math_op(&Minus, &from_i64(0), &product, Wrapping)
+    );
+    assert_eq!(Ok(-(&big)), eval_negate(&big, Saturating));
+    assert_eq!(
+        Ok(product),
+        eval_bin_math_op(&Power, &big, &from_i64(2), Checked)
+    );
+    assert_eq!(
+        Ok(from_i64(-1)),
+        eval_bin_math_op(&Power, &from_i64(-1), &(&big * &big + 1), Checked)
+    );
+    assert_eq!(
+        Err(ArithmeticError::Overflow),
+        eval_bin_math_op(&Power, &from_i64(2), &from_i64(4_000_000_000), Checked)
+    );
+    assert_eq!(
+        Err(ArithmeticError::Overflow),
+        eval_bin_math_op(&Power, &big, &from_i64(1 << 15), Checked)
+    );
+}
+
+#[test]
+fn test_builtins() {
+    let env = EvalEnv::new(Arithmetic::Checked);
+    let apply = |f: &str, args: &[i64]| {
+        let term = args
+            .iter()
+            .fold(TermKind::Var(f.to_string()).into(), |acc, n| {
+                Term::from(TermKind::Apply {
+                    var_term: Box::new(TermKind::NumConst(from_i64(*n)).into()),
+                    function: Box::new(acc),
+                })
+            });
+        eval(&term, &env)
+    };
+    assert_eq!(Ok(Value::Num(from_i64(-2))), apply("min", &[3, -2]));
+    assert_eq!(Ok(Value::Num(from_i64(3))), apply("max", &[3, -2]));
+    assert_eq!(Ok(Value::Num(from_i64(5))), apply("abs", &[-5]));
+    assert_eq!(
+        Ok(Value::Builtin {
+            builtin: Builtin::Max,
+            args: vec![Value::Num(from_i64(3))]
+        }),
+        apply("max", &[3])
+    );
+    #[cfg(not(feature = "bigint"))]
+    assert_eq!(E_OVERFLOW, apply("abs", &[i64::MIN]).unwrap_err().code);
+}
+
+#[test]
+fn test_arithmetic_error() {
+    let negate: Term = TermKind::Negate(Box::new(TermKind::NumConst(from_i64(3)).into())).into();
+    let sub_expr: Term = TermKind::MathOp {
+        opr: BinMathOp::Minus,
+        t1: Box::new(negate),
+        t2: Box::new(TermKind::NumConst(from_i64(2)).into()),
+    }
+    .into();
+    assert_eq!(
+        Ok(Value::Num(from_i64(-5))),
+        eval(&sub_expr, &EvalEnv::new(Arithmetic::Checked))
+    );
+
+    let div_expr: Term = TermKind::MathOp {
+        opr: BinMathOp::Divide,
+        t1: Box::new(sub_expr),
+        t2: Box::new(TermKind::NumConst(from_i64(0)).into()),
+    }
+    .into();
+    let err = eval(&div_expr, &EvalEnv::new(Arithmetic::Saturating)).unwrap_err();
+    assert_eq!(E_DIVISION_BY_ZERO, err.code);
+    assert_eq!("`-5 / 0` divides by zero", err.message);
+}
+
+#[test]
+fn test_tuples() {
+    let num = |n| -> Term { TermKind::NumConst(from_i64(n)).into() };
+    let tuple = |components| -> Box<Term> { Box::new(TermKind::Tuple(components).into()) };
+    let env = EvalEnv::new(Arithmetic::Checked);
+    let nested = tuple(vec![num(1), *tuple(vec![num(2), num(3)])]);
+    let project: Term = TermKind::Project {
+        tuple: Box::new(
+            TermKind::Project {
+                tuple: nested.clone(),
+                index: 1,
+            }
+            .into(),
+        ),
+        index: 0,
+    }
+    .into();
+    assert_eq!(Ok(Value::Num(from_i64(2))), eval(&project, &env));
+
+    let compare = |other| {
+        let equals: Term = TermKind::Equals {
+            left_side: nested.clone(),
+            right_side: other,
+        }
+        .into();
+        eval(&equals, &env)
     };
-    let if_2 = Term::IfStmt {
-        test: Box::new(Term::NumConst(1)),
-        then_body: Box::new(Term::NumConst(6)),
-        else_body: Box::new(Term::NumConst(7)),
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        compare(tuple(vec![num(1), *tuple(vec![num(2), num(3)])]))
+    );
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        compare(tuple(vec![num(1), *tuple(vec![num(2), num(4)])]))
+    );
+}
+
+#[test]
+fn test_records() {
+    let record = |x, flag| -> Box<Term> {
+        Box::new(
+            TermKind::Record(vec![
+                ("x".to_string(), TermKind::NumConst(from_i64(x)).into()),
+                ("flag".to_string(), TermKind::BoolConst(flag).into()),
+            ])
+            .into(),
+        )
     };
-    let if_3 = Term::IfStmt {
-        test: Box::new(Term::BoolConst(false)),
-        then_body: Box::new(Term::NumConst(6)),
-        else_body: Box::new(Term::NumConst(7)),
+    let env = EvalEnv::new(Arithmetic::Checked);
+    let field: Term = TermKind::Field {
+        record: record(4, true),
+        field: "x".to_string(),
+    }
+    .into();
+    assert_eq!(Ok(Value::Num(from_i64(4))), eval(&field, &env));
+
+    let equals = |left, right| {
+        let term: Term = TermKind::Equals {
+            left_side: left,
+            right_side: right,
+        }
+        .into();
+        eval(&term, &env)
     };
-    let env = EvalEnv(HashMap::new());
-    assert_eq!(Ok(Value::Num(6)), eval(&if_1, &env));
     assert_eq!(
-        Err(EVAL_IF_COND_REQUIRES_BOOL.to_string()),
-        eval(&if_2, &env)
+        Ok(Value::Bool(true)),
+        equals(record(1, false), record(1, false))
+    );
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        equals(record(1, false), record(1, true))
+    );
+}
+
+#[test]
+fn test_data() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "data Shape = Circle Int | Rect Int Int | Empty;\
+         case Rect 2 3 of Circle r -> 3 * r * r | Rect w h -> w * h | Empty -> 0 end;\
+         case Empty of Circle r -> r | Rect w _ -> w | Empty -> 0 end;\
+         Rect 2;",
+    )
+    .unwrap();
+    let mut env = EvalEnv::new(Arithmetic::Checked);
+    match &terms[0].kind {
+        TermKind::Data { constructors, .. } => env.vars.extend(eval_data(constructors)),
+        _ => unreachable!(),
+    }
+    assert_eq!(Ok(Value::Num(from_i64(6))), eval(&terms[1], &env));
+    assert_eq!(Ok(Value::Num(from_i64(0))), eval(&terms[2], &env));
+    assert_eq!(
+        Ok(Value::Constructor {
+            name: "Rect".to_string(),
+            arity: 2,
+            args: vec![Value::Num(from_i64(2))]
+        }),
+        eval(&terms[3], &env)
+    );
+
+    // Closures print the same wherever their source is.
+    let closure = |source: &str| {
+        let terms = parse_file(0, source).unwrap();
+        format!("{:?}", eval(&terms[0], &env).unwrap())
+    };
+    let printed = closure("\\s. case s of Empty -> 1 | Circle r -> r end;");
+    assert_eq!(
+        printed,
+        closure("   \\s. case s of Empty -> 1 | Circle r -> r end;")
+    );
+    assert!(!printed.contains("span"), "{}", printed);
+}
+
+#[test]
+fn test_match() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "match 2 with 1 -> 10 | n -> n * 3 | 2 -> 0 end;\
+         match 1 < 2 with false -> 0 | true -> 1 end;\
+         match 5 with 1 -> 0 end;",
+    )
+    .unwrap();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(Ok(Value::Num(from_i64(6))), eval(&terms[0], &env));
+    assert_eq!(Ok(Value::Num(from_i64(1))), eval(&terms[1], &env));
+    assert_eq!(EVAL_MATCH_ERROR, eval(&terms[2], &env).unwrap_err().message);
+
+    // As with `case`, closures print the same wherever their source is.
+    let closure = |source: &str| {
+        let terms = parse_file(0, source).unwrap();
+        format!("{:?}", eval(&terms[0], &env).unwrap())
+    };
+    let printed = closure("\\n. match n with 0 -> 1 | m -> m end;");
+    assert_eq!(
+        printed,
+        closure("   \\n. match n with 0 -> 1 | m -> m end;")
+    );
+    assert!(!printed.contains("span"), "{}", printed);
+}
+
+#[test]
+fn test_lists() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "0 :: [1, 2];\
+         match [1, 2, 3] with [] -> 0 | [x] -> x | x :: y :: _ -> x + y end;\
+         (length [true, false], isEmpty [], head (tail [1, 2]));\
+         [[1], []] == [[1], []];\
+         [1, 2] == [1];\
+         tail [];",
+    )
+    .unwrap();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    let num = |n| Value::Num(from_i64(n));
+    assert_eq!(
+        Ok(Value::List(vec![num(0), num(1), num(2)])),
+        eval(&terms[0], &env)
+    );
+    assert_eq!(Ok(num(3)), eval(&terms[1], &env));
+    assert_eq!(
+        Ok(Value::Tuple(vec![num(2), Value::Bool(true), num(2)])),
+        eval(&terms[2], &env)
+    );
+    assert_eq!(Ok(Value::Bool(true)), eval(&terms[3], &env));
+    assert_eq!(Ok(Value::Bool(false)), eval(&terms[4], &env));
+    let e = eval(&terms[5], &env).unwrap_err();
+    assert_eq!(
+        (E_EMPTY_LIST, "`tail` of an empty list"),
+        (e.code, e.message.as_str())
+    );
+}
+
+#[test]
+fn test_strings() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "\"λ = \" ++ show (-1) ++ \", \" ++ show false;\
+         (length \"λx\\n\", \"ab\" == \"a\" ++ \"b\", [\"a\"] == [\"b\"]);",
+    )
+    .unwrap();
+    let env = EvalEnv::new(Arithmetic::Checked);
+    assert_eq!(
+        Ok(Value::Str("λ = -1, false".to_string())),
+        eval(&terms[0], &env)
+    );
+    assert_eq!(
+        Ok(Value::Tuple(vec![
+            Value::Num(from_i64(3)),
+            Value::Bool(true),
+            Value::Bool(false)
+        ])),
+        eval(&terms[1], &env)
     );
-    assert_eq!(Ok(Value::Num(7)), eval(&if_3, &env));
 }
@@ -0,0 +1,303 @@
+//! Checks that every `match` has an arm for every value it can be given, and
+//! finds the arms that can never be reached.
+//!
+//! This runs once the whole program is well-typed, so the patterns of each
+//! `match` are all of one type. A `case` is checked by the type checker
+//! instead, which knows the constructors of its data type.
+//!
+//! Patterns can nest, so both checks ask whether some value is matched by a
+//! pattern but by none of the patterns before it, following Maranget's
+//! "Warnings for pattern matching". The values of a type are sorted by their
+//! outermost constructor: `true` or `false`, `[]` or `::`, or a number. Only
+//! numbers cannot all be listed, and need a catch-all.
+
+use ast::{MatchArm, Pattern, Span, Term, TermKind};
+use num::Num;
+use type_check::{TypeError, E_NON_EXHAUSTIVE};
+
+// Codes reported for warnings.
+pub const W_UNREACHABLE_ARM: &str = "W0001";
+
+/// A problem that is reported, but does not stop the program from running.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Warning {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+/// Checks every `match` within `term`, and adds a warning to `warnings` for
+/// each arm that can never be reached.
+pub fn check_matches(term: &Term, warnings: &mut Vec<Warning>) -> Result<(), TypeError> {
+    if let TermKind::Match { arms, .. } = &term.kind {
+        check_arms(arms, term.span, warnings)?;
+    }
+    for subterm in term.subterms() {
+        check_matches(subterm, warnings)?;
+    }
+    Ok(())
+}
+
+/// Checks the arms of the `match` at `span`.
+fn check_arms(arms: &[MatchArm], span: Span, warnings: &mut Vec<Warning>) -> Result<(), TypeError> {
+    let mut rows: Vec<Vec<&Pattern>> = Vec::new();
+    for arm in arms {
+        if !useful(&rows, &[&arm.pattern]) {
+            let help = if missing(&rows, 1).is_none() {
+                "the arms before it already match every value".to_string()
+            } else {
+                "the arms before it already match everything it matches".to_string()
+            };
+            warnings.push(Warning {
+                code: W_UNREACHABLE_ARM,
+                message: format!("the arm for `{}` is unreachable", arm.pattern),
+                span: arm.span,
+                help: Some(help),
+            });
+        }
+        rows.push(vec![&arm.pattern]);
+    }
+
+    match missing(&rows, 1).map(|mut witness| witness.remove(0)) {
+        None => Ok(()),
+        Some(Pattern::Wildcard) => Err(TypeError::new(
+            E_NON_EXHAUSTIVE,
+            "`match` on an Int has no catch-all arm",
+            span,
+        )
+        .with_help("add `| _ -> ...` or `| n -> ...` as the last arm")),
+        Some(witness) => Err(TypeError::new(
+            E_NON_EXHAUSTIVE,
+            format!("`match` does not handle `{}`", witness),
+            span,
+        )
+        .with_help(format!("add an arm for `{}`, or a `_` arm", witness))),
+    }
+}
+
+const WILDCARD: &Pattern = &Pattern::Wildcard;
+
+/// The outermost constructor of a pattern that is not a catch-all.
+#[derive(PartialEq, Clone, Copy)]
+enum Constructor<'a> {
+    Num(&'a Num),
+    Bool(bool),
+    Nil,
+    Cons,
+}
+
+impl<'a> Constructor<'a> {
+    fn of(pattern: &'a Pattern) -> Option<Constructor<'a>> {
+        match pattern {
+            Pattern::Num(n) => Some(Constructor::Num(n)),
+            Pattern::Bool(b) => Some(Constructor::Bool(*b)),
+            Pattern::Nil => Some(Constructor::Nil),
+            Pattern::Cons(..) => Some(Constructor::Cons),
+            Pattern::Wildcard | Pattern::Var(_) => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Constructor::Cons => 2,
+            _ => 0,
+        }
+    }
+
+    /// The pattern that builds this constructor around `fields`.
+    fn build(self, mut fields: Vec<Pattern>) -> Pattern {
+        match self {
+            Constructor::Num(n) => Pattern::Num(n.to_owned()),
+            Constructor::Bool(b) => Pattern::Bool(b),
+            Constructor::Nil => Pattern::Nil,
+            Constructor::Cons => {
+                let tail = fields.pop().unwrap();
+                Pattern::Cons(Box::new(fields.pop().unwrap()), Box::new(tail))
+            }
+        }
+    }
+}
+
+/// All of the constructors of the type of the first column of `rows`, if
+/// the patterns there use every one of them. Numbers never run out.
+fn complete<'a>(rows: &[Vec<&'a Pattern>]) -> Option<Vec<Constructor<'a>>> {
+    let used: Vec<Constructor> = rows
+        .iter()
+        .filter_map(|row| Constructor::of(row[0]))
+        .collect();
+    let all = match used.first()? {
+        Constructor::Bool(_) => vec![Constructor::Bool(false), Constructor::Bool(true)],
+        Constructor::Nil | Constructor::Cons => vec![Constructor::Nil, Constructor::Cons],
+        Constructor::Num(_) => return None,
+    };
+    if all.iter().all(|c| used.contains(c)) {
+        Some(all)
+    } else {
+        None
+    }
+}
+
+/// A pattern for some value of the first column of `rows` that is not built
+/// by any of the constructors there, when they are not `complete`.
+fn other(rows: &[Vec<&Pattern>]) -> Pattern {
+    match rows.iter().find_map(|row| Constructor::of(row[0])) {
+        Some(Constructor::Bool(b)) => Pattern::Bool(!b),
+        Some(Constructor::Nil) => {
+            Pattern::Cons(Box::new(Pattern::Wildcard), Box::new(Pattern::Wildcard))
+        }
+        Some(Constructor::Cons) => Pattern::Nil,
+        Some(Constructor::Num(_)) | None => Pattern::Wildcard,
+    }
+}
+
+/// The rows that match a value built by `c`, with the first pattern of each
+/// replaced by patterns for the fields of `c`.
+fn specialize<'a>(rows: &[Vec<&'a Pattern>], c: Constructor) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut fields: Vec<&Pattern> = match row[0] {
+                Pattern::Wildcard | Pattern::Var(_) => vec![WILDCARD; c.arity()],
+                Pattern::Cons(head, tail) if c == Constructor::Cons => vec![head, tail],
+                p if Constructor::of(p) == Some(c) => Vec::new(),
+                _ => return None,
+            };
+            fields.extend(&row[1..]);
+            Some(fields)
+        })
+        .collect()
+}
+
+/// The rows whose first pattern matches anything, without that pattern.
+fn default<'a>(rows: &[Vec<&'a Pattern>]) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .filter(|row| Constructor::of(row[0]).is_none())
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+/// Whether some values are matched by `patterns` but by none of `rows`.
+fn useful(rows: &[Vec<&Pattern>], patterns: &[&Pattern]) -> bool {
+    if patterns.is_empty() {
+        return rows.is_empty();
+    }
+    let constructors = match Constructor::of(patterns[0]) {
+        Some(c) => vec![c],
+        None => match complete(rows) {
+            Some(all) => all,
+            None => return useful(&default(rows), &patterns[1..]),
+        },
+    };
+    constructors.into_iter().any(|c| {
+        let patterns = specialize(&[patterns.to_vec()], c).remove(0);
+        useful(&specialize(rows, c), &patterns)
+    })
+}
+
+/// Patterns for values, `width` of them side by side, that none of `rows`
+/// matches, if there are any.
+fn missing(rows: &[Vec<&Pattern>], width: usize) -> Option<Vec<Pattern>> {
+    if width == 0 {
+        return if rows.is_empty() {
+            Some(Vec::new())
+        } else {
+            None
+        };
+    }
+    match complete(rows) {
+        Some(all) => all.into_iter().find_map(|c| {
+            let mut fields = missing(&specialize(rows, c), c.arity() + width - 1)?;
+            let rest = fields.split_off(c.arity());
+            let mut witness = vec![c.build(fields)];
+            witness.extend(rest);
+            Some(witness)
+        }),
+        None => {
+            let mut witness = missing(&default(rows), width - 1)?;
+            witness.insert(0, other(rows));
+            Some(witness)
+        }
+    }
+}
+
+#[test]
+fn test_check_matches() {
+    use parse::parse_file;
+
+    let check = |src| {
+        let terms = parse_file(0, src).unwrap();
+        let mut warnings = Vec::new();
+        check_matches(&terms[0], &mut warnings).map(|()| {
+            warnings
+                .into_iter()
+                .map(|w| (w.span.start, w.message))
+                .collect::<Vec<_>>()
+        })
+    };
+    let message = |src| check(src).map_err(|e| (e.code, e.message));
+    assert_eq!(
+        Ok(vec![]),
+        check("match b with | true -> 1 | false -> 0 end;")
+    );
+    assert_eq!(
+        Ok(vec![]),
+        check("match n with | 0 -> 1 | -1 -> 2 | m -> m end;")
+    );
+    assert_eq!(
+        Err((
+            E_NON_EXHAUSTIVE,
+            "`match` does not handle `false`".to_string()
+        )),
+        message("match b with | true -> 1 end;")
+    );
+    assert_eq!(
+        Err((
+            E_NON_EXHAUSTIVE,
+            "`match` on an Int has no catch-all arm".to_string()
+        )),
+        message("match n with | 0 -> 1 | 1 -> 2 end;")
+    );
+    assert_eq!(
+        Ok(vec![
+            (24, "the arm for `0` is unreachable".to_string()),
+            (42, "the arm for `n` is unreachable".to_string()),
+        ]),
+        check("match n with | 0 -> 1 | 0 -> 2 | _ -> 3 | n -> n end;")
+    );
+    assert_eq!(
+        Ok(vec![(38, "the arm for `_` is unreachable".to_string())]),
+        check("match b with false -> 0 | true -> 1 | _ -> 2 end;")
+    );
+    // Matches within other terms are checked too.
+    assert_eq!(
+        Ok(vec![(27, "the arm for `x` is unreachable".to_string())]),
+        check("(\\b. match b with _ -> 0 | x -> 1 end);")
+    );
+
+    assert_eq!(
+        Ok(vec![]),
+        check("match l with | [] -> 0 | [x] -> x | x :: y :: rest -> y end;")
+    );
+    assert_eq!(
+        Err((
+            E_NON_EXHAUSTIVE,
+            "`match` does not handle `[_]`".to_string()
+        )),
+        message("match l with | [] -> 0 | x :: y :: rest -> y end;")
+    );
+    assert_eq!(
+        Err((E_NON_EXHAUSTIVE, "`match` does not handle `[]`".to_string())),
+        message("match l with | x :: _ -> x end;")
+    );
+    assert_eq!(
+        Err((
+            E_NON_EXHAUSTIVE,
+            "`match` does not handle `[false]`".to_string()
+        )),
+        message("match l with | [] -> 0 | true :: _ -> 1 | _ :: _ :: _ -> 2 end;")
+    );
+    assert_eq!(
+        Ok(vec![(36, "the arm for `[_]` is unreachable".to_string())]),
+        check("match l with | [] -> 0 | [x] -> x | [_] -> 1 | _ -> 2 end;")
+    );
+}
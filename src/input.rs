@@ -0,0 +1,197 @@
+use ast::{FileId, Span};
+use nom::{
+    AtEof, Compare, CompareResult, FindSubstring, FindToken, InputIter, InputLength, InputTake,
+    Offset, Slice, UnspecializedInput,
+};
+use std::ops::{Deref, Range, RangeFrom, RangeFull, RangeTo};
+use std::str::{CharIndices, Chars};
+
+/// A complete piece of source text that remembers its byte offset from the
+/// start of the file, so that parsers can report where they are.
+///
+/// This behaves like nom's `CompleteStr`: `at_eof` is always true.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Input<'a> {
+    pub fragment: &'a str,
+    pub offset: usize,
+    pub file: FileId,
+}
+
+impl<'a> Input<'a> {
+    pub fn new(file: FileId, fragment: &'a str) -> Input<'a> {
+        Input {
+            fragment,
+            offset: 0,
+            file,
+        }
+    }
+
+    /// The span of source covered by this fragment.
+    pub fn span(&self) -> Span {
+        Span {
+            file: self.file,
+            start: self.offset,
+            end: self.offset + self.fragment.len(),
+        }
+    }
+}
+
+impl<'a> From<Input<'a>> for &'a str {
+    fn from(input: Input<'a>) -> &'a str {
+        input.fragment
+    }
+}
+
+/// Returns the 1-based line and column of a byte offset into `source`.
+/// Columns count characters, not bytes.
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+impl<'a> Deref for Input<'a> {
+    type Target = &'a str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fragment
+    }
+}
+
+impl<'a> AtEof for Input<'a> {
+    fn at_eof(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> Slice<Range<usize>> for Input<'a> {
+    fn slice(&self, range: Range<usize>) -> Self {
+        Input {
+            offset: self.offset + range.start,
+            fragment: self.fragment.slice(range),
+            ..*self
+        }
+    }
+}
+
+impl<'a> Slice<RangeTo<usize>> for Input<'a> {
+    fn slice(&self, range: RangeTo<usize>) -> Self {
+        Input {
+            fragment: self.fragment.slice(range),
+            ..*self
+        }
+    }
+}
+
+impl<'a> Slice<RangeFrom<usize>> for Input<'a> {
+    fn slice(&self, range: RangeFrom<usize>) -> Self {
+        Input {
+            offset: self.offset + range.start,
+            fragment: self.fragment.slice(range),
+            ..*self
+        }
+    }
+}
+
+impl<'a> Slice<RangeFull> for Input<'a> {
+    fn slice(&self, _: RangeFull) -> Self {
+        *self
+    }
+}
+
+impl<'a> InputIter for Input<'a> {
+    type Item = char;
+    type RawItem = char;
+    type Iter = CharIndices<'a>;
+    type IterElem = Chars<'a>;
+
+    fn iter_indices(&self) -> Self::Iter {
+        self.fragment.iter_indices()
+    }
+    fn iter_elements(&self) -> Self::IterElem {
+        self.fragment.iter_elements()
+    }
+    fn position<P>(&self, predicate: P) -> Option<usize>
+    where
+        P: Fn(Self::RawItem) -> bool,
+    {
+        self.fragment.position(predicate)
+    }
+    fn slice_index(&self, count: usize) -> Option<usize> {
+        self.fragment.slice_index(count)
+    }
+}
+
+impl<'a> InputTake for Input<'a> {
+    fn take(&self, count: usize) -> Self {
+        self.slice(..count)
+    }
+
+    fn take_split(&self, count: usize) -> (Self, Self) {
+        (self.slice(count..), self.slice(..count))
+    }
+}
+
+impl<'a> InputLength for Input<'a> {
+    fn input_len(&self) -> usize {
+        self.fragment.len()
+    }
+}
+
+impl<'a> UnspecializedInput for Input<'a> {}
+
+impl<'a, 'b> Compare<&'b str> for Input<'a> {
+    fn compare(&self, t: &'b str) -> CompareResult {
+        self.fragment.compare(t)
+    }
+    fn compare_no_case(&self, t: &'b str) -> CompareResult {
+        self.fragment.compare_no_case(t)
+    }
+}
+
+impl<'a, 'b> FindSubstring<&'b str> for Input<'a> {
+    fn find_substring(&self, substr: &'b str) -> Option<usize> {
+        self.fragment.find_substring(substr)
+    }
+}
+
+impl<'a> FindToken<char> for Input<'a> {
+    fn find_token(&self, token: char) -> bool {
+        self.fragment.find_token(token)
+    }
+}
+
+impl<'a> Offset for Input<'a> {
+    fn offset(&self, second: &Self) -> usize {
+        second.offset - self.offset
+    }
+}
+
+#[test]
+fn test_slice_offsets() {
+    let input = Input::new(0, "a := 1;");
+    let rest = input.slice(5..);
+    assert_eq!(rest.fragment, "1;");
+    assert_eq!(rest.offset, 5);
+    assert_eq!(rest.slice(1..).offset, 6);
+    assert_eq!(input.offset(&rest), 5);
+    assert_eq!(
+        Span {
+            file: 0,
+            start: 5,
+            end: 7
+        },
+        rest.span()
+    );
+}
+
+#[test]
+fn test_line_col() {
+    let source = "a := 1;\nb := a\n  + 2;";
+    assert_eq!((1, 1), line_col(source, 0));
+    assert_eq!((1, 6), line_col(source, 5));
+    assert_eq!((2, 1), line_col(source, 8));
+    assert_eq!((3, 3), line_col(source, 17));
+}
@@ -0,0 +1,308 @@
+//! Splits source text into the tokens that the parser reads.
+
+use ast::{FileId, Span};
+use input::Input;
+use nom::{digit, AtEof, Context, Err, ErrorKind, IResult, InputLength, InputTake, Slice};
+use regex::Regex;
+use std::ops::RangeFrom;
+use std::sync::OnceLock;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenKind {
+    /// A name, such as `x` or `Int`.
+    Name,
+    /// A word that cannot be used as a name, such as `if`.
+    Keyword,
+    /// Decimal digits. A minus sign in front of them is a separate token.
+    Number,
+    /// A string literal as it is written, with its quotes and escapes. One
+    /// that is not closed on its line runs to the end of the line, and is
+    /// left for the parser to report.
+    Str,
+    /// An operator or punctuation, such as `:=` or `(`. `{-` always starts
+    /// a comment, never a `{` symbol.
+    Symbol,
+    /// A character that starts no other token, left for the parser to
+    /// report.
+    Unknown,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    fn new(kind: TokenKind, text: Input<'a>) -> Token<'a> {
+        Token {
+            kind,
+            text: text.fragment,
+            span: text.span(),
+        }
+    }
+}
+
+// Words that cannot be used as names.
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "endif", "true", "false", "let", "in", "rec", "and", "data", "case",
+    "of", "end", "match", "with",
+];
+
+// Symbols that start with another symbol come before it, so that the longest
+// one is taken: `:=` is one token, not `:` and `=`.
+const SYMBOLS: &[&str] = &[
+    ":=", "::", "->", "==", "!=", "++", "<=", ">=", "&&", "||", "\\", "λ", "→", ".", "(", ")", "{",
+    "}", "[", "]", ",", ":", ";", "=", "+", "-", "*", "/", "%", "^", "!", "<", ">", "|",
+];
+
+/// A block comment that is still open at the end of the file.
+#[derive(PartialEq, Eq, Debug)]
+pub struct UnclosedComment {
+    /// The span of the `{-` that opens the comment.
+    pub span: Span,
+}
+
+/// Returns the length of the whitespace and comments at the start of `text`,
+/// or, if a block comment is still open at the end of it, the offset of the
+/// `{-` that opens that comment.
+fn space_len(text: &str) -> Result<usize, usize> {
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+        if rest.starts_with("--") {
+            rest = rest.find('\n').map_or("", |i| &rest[i..]);
+        } else if rest.starts_with("{-") {
+            let start = text.len() - rest.len();
+            let mut depth = 0;
+            loop {
+                if rest.starts_with("{-") {
+                    depth += 1;
+                    rest = &rest[2..];
+                } else if rest.starts_with("-}") {
+                    depth -= 1;
+                    rest = &rest[2..];
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    let c = rest.chars().next().ok_or(start)?;
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        } else {
+            return Ok(text.len() - rest.len());
+        }
+    }
+}
+
+// A name starts with a Unicode letter or `_`, and goes on with letters,
+// digits and `_`. `λ` is excluded from the start, so that `λx. x` is a lambda.
+fn identifier(input: Input) -> IResult<Input, Input> {
+    static IDENTIFIER: OnceLock<Regex> = OnceLock::new();
+    let identifier =
+        IDENTIFIER.get_or_init(|| Regex::new(r"^[\p{XID_Start}_--λ]\p{XID_Continue}*").unwrap());
+    match identifier.find(&input) {
+        Some(m) => Ok((input.slice(m.end()..), input.slice(..m.end()))),
+        None => Err(Err::Error(Context::Code(input, ErrorKind::RegexpFind))),
+    }
+}
+
+// A string runs to the next `"` that is not escaped, and cannot span lines.
+fn string(input: Input) -> IResult<Input, Input> {
+    static STRING: OnceLock<Regex> = OnceLock::new();
+    let string = STRING.get_or_init(|| Regex::new(r#"^"([^"\\\n]|\\.)*"?"#).unwrap());
+    match string.find(&input) {
+        Some(m) => Ok((input.slice(m.end()..), input.slice(..m.end()))),
+        None => Err(Err::Error(Context::Code(input, ErrorKind::RegexpFind))),
+    }
+}
+
+fn symbol(input: Input) -> IResult<Input, Input> {
+    match SYMBOLS.iter().find(|symbol| input.starts_with(*symbol)) {
+        Some(symbol) => Ok((input.slice(symbol.len()..), input.slice(..symbol.len()))),
+        None => Err(Err::Error(Context::Code(input, ErrorKind::Tag))),
+    }
+}
+
+named!(token<Input, Token>, alt!(
+    map!(identifier, |text| {
+        let kind = if KEYWORDS.contains(&text.fragment) {
+            TokenKind::Keyword
+        } else {
+            TokenKind::Name
+        };
+        Token::new(kind, text)
+    })
+    | map!(digit, |text| Token::new(TokenKind::Number, text))
+    | map!(string, |text| Token::new(TokenKind::Str, text))
+    | map!(symbol, |text| Token::new(TokenKind::Symbol, text))
+    | map!(take!(1), |text| Token::new(TokenKind::Unknown, text))));
+
+/// Splits `contents` into tokens, skipping whitespace, `--` line comments and
+/// `{- -}` block comments. Each token is the longest one that fits.
+pub fn lex<'a>(file: FileId, contents: &'a str) -> Result<Vec<Token<'a>>, UnclosedComment> {
+    let mut input = Input::new(file, contents);
+    let mut tokens = Vec::new();
+    loop {
+        let len = space_len(&input).map_err(|start| UnclosedComment {
+            span: input.slice(start..start + 2).span(),
+        })?;
+        input = input.slice(len..);
+        if input.is_empty() {
+            return Ok(tokens);
+        }
+        let (rest, token) = token(input).expect("every character starts a token");
+        tokens.push(token);
+        input = rest;
+    }
+}
+
+/// The tokens that are left to parse. Like `Input`, this is always complete.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Tokens<'a> {
+    pub tokens: &'a [Token<'a>],
+    /// The empty span at the end of the file, where running out of tokens is
+    /// reported.
+    pub end: Span,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(tokens: &'a [Token<'a>], end: Span) -> Tokens<'a> {
+        Tokens { tokens, end }
+    }
+
+    /// The span of the next token, or the end of the file if there is none.
+    pub fn span(&self) -> Span {
+        self.tokens.first().map_or(self.end, |token| token.span)
+    }
+}
+
+impl<'a> AtEof for Tokens<'a> {
+    fn at_eof(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> InputLength for Tokens<'a> {
+    fn input_len(&self) -> usize {
+        self.tokens.len()
+    }
+}
+
+impl<'a> Slice<RangeFrom<usize>> for Tokens<'a> {
+    fn slice(&self, range: RangeFrom<usize>) -> Self {
+        Tokens {
+            tokens: &self.tokens[range],
+            ..*self
+        }
+    }
+}
+
+impl<'a> InputTake for Tokens<'a> {
+    fn take(&self, count: usize) -> Self {
+        Tokens {
+            tokens: &self.tokens[..count],
+            ..*self
+        }
+    }
+
+    fn take_split(&self, count: usize) -> (Self, Self) {
+        (self.slice(count..), self.take(count))
+    }
+}
+
+#[test]
+fn test_lex() {
+    use self::TokenKind::*;
+
+    let kinds = |src| -> Vec<(TokenKind, &str)> {
+        lex(0, src)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    };
+    assert_eq!(
+        vec![
+            (Name, "iffy"),
+            (Symbol, ":="),
+            (Keyword, "if"),
+            (Name, "trueValue"),
+            (Symbol, "=="),
+            (Symbol, "-"),
+            (Number, "12"),
+            (Keyword, "then"),
+            (Name, "false_case"),
+            (Keyword, "else"),
+            (Unknown, "#"),
+        ],
+        kinds("iffy:=if trueValue==-12 then false_case else #")
+    );
+    assert_eq!(
+        vec![
+            (Symbol, "λ"),
+            (Name, "α"),
+            (Symbol, "."),
+            (Symbol, "->"),
+            (Symbol, "→"),
+            (Symbol, "<="),
+            (Symbol, "<"),
+            (Symbol, "!"),
+            (Number, "1"),
+        ],
+        kinds("λα. -> → <=< -- line\n {- block {- nested -} -} !1")
+    );
+    assert_eq!(
+        vec![
+            (Keyword, "case"),
+            (Name, "x"),
+            (Keyword, "of"),
+            (Symbol, "|"),
+            (Symbol, "||"),
+            (Keyword, "end"),
+            (Keyword, "endif"),
+            (Name, "ending"),
+        ],
+        kinds("case x of | || end endif ending")
+    );
+    assert_eq!(
+        vec![
+            (Symbol, "["),
+            (Name, "x"),
+            (Symbol, "]"),
+            (Symbol, "::"),
+            (Symbol, ":="),
+            (Symbol, ":"),
+        ],
+        kinds("[x]:::=:")
+    );
+    assert_eq!(
+        vec![
+            (Str, r#""a \"b\" -- c""#),
+            (Symbol, "++"),
+            (Str, r#""unclosed \""#),
+            (Name, "x"),
+        ],
+        kinds("\"a \\\"b\\\" -- c\"++\"unclosed \\\"\nx")
+    );
+    assert_eq!(
+        Span {
+            file: 0,
+            start: 5,
+            end: 7
+        },
+        lex(0, "a := λ;").unwrap()[2].span
+    );
+    assert_eq!(
+        Err(UnclosedComment {
+            span: Span {
+                file: 0,
+                start: 2,
+                end: 4
+            }
+        }),
+        lex(0, "1 {- {- -}")
+    );
+}
@@ -1,70 +1,264 @@
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "bigint")]
+extern crate num_bigint;
 extern crate regex;
 
 mod ast;
+mod builtin;
+mod depend;
+mod diagnostic;
 mod eval;
+mod exhaustive;
+mod input;
+mod lex;
+mod num;
 mod parse;
 mod type_check;
 mod value;
 
-use eval::eval;
+use depend::Component;
+use diagnostic::Diagnostic;
+use eval::{eval, eval_data, eval_rec_group, Arithmetic};
 use parse::parse_file;
 use std::collections::HashMap;
 use std::io::{self, Read, Write};
-use type_check::type_check;
+use type_check::{type_check, type_check_data, type_check_definition, type_check_rec_group};
 
-fn err_str<E>(msg: E) -> io::Error
-where
-    E: Into<Box<std::error::Error + Send + Sync>>,
-{
-    io::Error::new(io::ErrorKind::InvalidInput, msg)
+/// The exit code for a command line that cannot be understood, or whose file
+/// cannot be read.
+const USAGE_EXIT_CODE: i32 = 1;
+
+/// The ways running a program can fail: either the program itself is wrong,
+/// or its output could not be written.
+enum ExecError {
+    Program(Diagnostic),
+    Io(io::Error),
 }
 
-fn exec(contents: &str, output: &mut Write) -> io::Result<()> {
-    let terms = parse_file(&contents).map_err(err_str)?;
+impl From<parse::ParseError> for ExecError {
+    fn from(e: parse::ParseError) -> ExecError {
+        ExecError::Program(e.into())
+    }
+}
 
-    let mut type_env = type_check::TyEnv(HashMap::new());
-    for term in &terms {
-        match term {
-            ast::Term::Assignm { var_name, expr } => {
-                let expr_type = type_check(expr, &type_env).map_err(err_str)?;
-                type_env.0.insert(var_name.to_string(), expr_type);
+impl From<type_check::TypeError> for ExecError {
+    fn from(e: type_check::TypeError) -> ExecError {
+        ExecError::Program(e.into())
+    }
+}
+
+impl From<eval::EvalError> for ExecError {
+    fn from(e: eval::EvalError) -> ExecError {
+        ExecError::Program(e.into())
+    }
+}
+
+/// Runs `contents`, printing values to `output`. Warnings are passed to
+/// `warn` once the program has been checked, before it runs.
+fn exec(
+    contents: &str,
+    arithmetic: Arithmetic,
+    output: &mut dyn Write,
+    warn: &mut dyn FnMut(Diagnostic) -> io::Result<()>,
+) -> Result<(), ExecError> {
+    let terms = parse_file(0, contents)?;
+    let order = depend::order(&terms)?;
+    let group = |members: &[usize]| -> Vec<(String, ast::Term)> {
+        members
+            .iter()
+            .map(|&i| match &terms[i].kind {
+                ast::TermKind::Assignm { var_name, expr } => (var_name.clone(), (**expr).clone()),
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+    let is_data = |members: &[usize]| matches!(terms[members[0]].kind, ast::TermKind::Data { .. });
+
+    let mut type_env = type_check::TyEnv {
+        vars: HashMap::new(),
+        arrow: parse::arrow_style(0, contents),
+    };
+    for component in &order {
+        match component {
+            Component::Single(i) => match &terms[*i].kind {
+                ast::TermKind::Assignm { var_name, expr } => {
+                    let scheme = type_check_definition(expr, &type_env)?;
+                    type_env.vars.insert(var_name.to_string(), scheme);
+                }
+                ast::TermKind::Data { .. } => {
+                    let schemes = type_check_data(&[&terms[*i]], &type_env)?;
+                    type_env.vars.extend(schemes);
+                }
+                _ => {
+                    type_check(&terms[*i], &type_env)?;
+                }
+            },
+            Component::Recursive(members) if is_data(members) => {
+                let decls: Vec<&ast::Term> = members.iter().map(|&i| &terms[i]).collect();
+                let schemes = type_check_data(&decls, &type_env)?;
+                type_env.vars.extend(schemes);
             }
-            _ => {
-                type_check(term, &type_env).map_err(err_str)?;
+            Component::Recursive(members) => {
+                let bindings = group(members);
+                let schemes = type_check_rec_group(&bindings, &type_env)?;
+                for ((name, _), scheme) in bindings.into_iter().zip(schemes) {
+                    type_env.vars.insert(name, scheme);
+                }
             }
-        };
+        }
     }
 
-    let mut eval_env = eval::EvalEnv(HashMap::new());
+    let mut warnings = Vec::new();
     for term in &terms {
-        let val = eval(term, &eval_env).map_err(err_str)?;
-        match val {
-            value::Value::Assignm { name, val } => {
-                eval_env.0.insert(name, *val);
+        exhaustive::check_matches(term, &mut warnings)?;
+    }
+    for warning in warnings {
+        warn(warning.into()).map_err(ExecError::Io)?;
+    }
+
+    let mut eval_env = eval::EvalEnv::new(arithmetic);
+    for component in &order {
+        match component {
+            Component::Single(i) => match &terms[*i].kind {
+                ast::TermKind::Data { constructors, .. } => {
+                    eval_env.vars.extend(eval_data(constructors));
+                }
+                _ => match eval(&terms[*i], &eval_env)? {
+                    value::Value::Assignm { name, val } => {
+                        eval_env.vars.insert(name, *val);
+                    }
+                    val => writeln!(output, "{:?}", val).map_err(ExecError::Io)?,
+                },
+            },
+            Component::Recursive(members) if is_data(members) => {
+                for &i in members {
+                    if let ast::TermKind::Data { constructors, .. } = &terms[i].kind {
+                        eval_env.vars.extend(eval_data(constructors));
+                    }
+                }
+            }
+            Component::Recursive(members) => {
+                let values = eval_rec_group(&group(members), &eval_env);
+                eval_env.vars.extend(values);
             }
-            _ => writeln!(output, "{:?}", val).or_else(|_| Err(err_str("write failed")))?,
         }
     }
 
     Ok(())
 }
 
-fn main() -> io::Result<()> {
-    let filename = std::env::args()
-        .nth(1)
-        .ok_or_else(|| err_str("No filename argument provided"))?;
-    let mut handle: Box<Read> = match filename.as_str() {
-        "-" => Box::new(std::io::stdin()),
-        _ => Box::new(std::fs::File::open(filename)?),
-    };
+/// Runs `contents`, printing values to `output` and any diagnostic to
+/// `errors`, and returns the exit code for the process.
+fn run(
+    contents: &str,
+    filename: &str,
+    arithmetic: Arithmetic,
+    output: &mut dyn Write,
+    errors: &mut dyn Write,
+) -> io::Result<i32> {
+    let mut warn = |warning: Diagnostic| write!(errors, "{}", warning.render(contents, filename));
+    match exec(contents, arithmetic, output, &mut warn) {
+        Ok(()) => Ok(0),
+        Err(ExecError::Program(diagnostic)) => {
+            write!(errors, "{}", diagnostic.render(contents, filename))?;
+            Ok(diagnostic.exit_code())
+        }
+        Err(ExecError::Io(e)) => Err(e),
+    }
+}
+
+// Recursion in a program is recursion in the evaluator, so it runs on a
+// thread with a much larger stack than the main thread's. The stack is only
+// reserved up front; it takes memory as calls use it. It has room for the
+// most calls eval allows at once, even in a debug build.
+const STACK_SIZE: usize = 1 << 32;
+
+/// Runs `f` on a thread with a stack of `STACK_SIZE` bytes.
+fn with_large_stack<T, F>(f: F) -> io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let thread = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)?;
+    Ok(thread
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e)))
+}
+
+fn main() {
+    let code = with_large_stack(interpret).unwrap_or_else(|e| {
+        eprintln!("error: cannot start the interpreter: {}", e);
+        USAGE_EXIT_CODE
+    });
+    std::process::exit(code);
+}
+
+/// Reads the arithmetic mode and the name of the file to run from `args`.
+fn parse_args<I>(mut args: I) -> Result<(Arithmetic, String), String>
+where
+    I: Iterator<Item = String>,
+{
+    let mut arithmetic = Arithmetic::default();
+    let mut filename = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--arithmetic" => {
+                arithmetic = args
+                    .next()
+                    .ok_or("`--arithmetic` needs a mode: checked, wrapping or saturating")?
+                    .parse()?;
+            }
+            _ => filename = Some(arg),
+        }
+    }
+    let filename = filename.ok_or("no file to run: pass its name, or `-` for stdin")?;
+    Ok((arithmetic, filename))
+}
+
+/// Reads the program to run from `filename`, or from stdin if it is `-`.
+fn read_program(filename: &str) -> Result<String, String> {
     let mut contents = String::new();
-    let _len = handle.read_to_string(&mut contents)?;
+    let read = match filename {
+        "-" => std::io::stdin().read_to_string(&mut contents),
+        _ => std::fs::File::open(filename).and_then(|mut file| file.read_to_string(&mut contents)),
+    };
+    read.map(|_| contents)
+        .map_err(|e| format!("cannot read `{}`: {}", filename, e))
+}
 
-    exec(&contents, &mut std::io::stdout())?;
+/// Runs the program named on the command line, and returns the exit code
+/// for the process.
+fn interpret() -> i32 {
+    let run_args = parse_args(std::env::args().skip(1)).and_then(|(arithmetic, filename)| {
+        read_program(&filename).map(|contents| (arithmetic, filename, contents))
+    });
+    let (arithmetic, filename, contents) = match run_args {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return USAGE_EXIT_CODE;
+        }
+    };
 
-    Ok(())
+    let name = if filename == "-" {
+        "<stdin>"
+    } else {
+        &filename
+    };
+    run(
+        &contents,
+        name,
+        arithmetic,
+        &mut std::io::stdout(),
+        &mut std::io::stderr(),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("error: cannot write output: {}", e);
+        USAGE_EXIT_CODE
+    })
 }
 
 #[cfg(test)]
@@ -79,15 +273,61 @@ fn test_script(path: &str) {
     expected_path.set_extension("out");
 
     let mut expected = Vec::new();
-    std::fs::File::open(expected_path)
+    std::fs::File::open(&expected_path)
         .unwrap()
         .read_to_end(&mut expected)
         .unwrap();
 
-    let mut output = Vec::new();
-    exec(&script, &mut output).unwrap();
+    // Diagnostics are expected after the output, and are rendered with the
+    // path of the script relative to the crate.
+    let name = std::path::Path::new(path).file_name().unwrap();
+    let name = format!("tests/{}", name.to_str().unwrap());
+    let script_name = name.clone();
+    let (code, output) = with_large_stack(move || {
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        let code = run(
+            &script,
+            &script_name,
+            Arithmetic::default(),
+            &mut output,
+            &mut errors,
+        )
+        .unwrap();
+        output.extend(errors);
+        (code, output)
+    })
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&expected),
+        String::from_utf8_lossy(&output)
+    );
+    assert_eq!(name.contains("fail"), code != 0);
+}
+
+#[test]
+fn test_parse_args() {
+    let parse = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(
+        Ok((Arithmetic::Wrapping, "a.lam".to_string())),
+        parse(&["--arithmetic", "wrapping", "a.lam"])
+    );
+    assert_eq!(
+        Err("unknown arithmetic mode `fast`: expected checked, wrapping or saturating".to_string()),
+        parse(&["--arithmetic", "fast", "a.lam"])
+    );
+    assert!(parse(&["a.lam", "--arithmetic"]).is_err());
+    assert!(parse(&[]).is_err());
+}
 
-    assert_eq!(expected, output);
+#[test]
+fn test_read_program() {
+    assert!(read_program("tests/ident.lam").is_ok());
+    let message = read_program("tests/missing.lam").unwrap_err();
+    assert!(message.starts_with("cannot read `tests/missing.lam`: "));
+    assert!(!message.contains('\n'));
+    assert!(read_program("tests").is_err());
 }
 
 include!(concat!(env!("OUT_DIR"), "/scripts.rs"));
@@ -0,0 +1,54 @@
+//! The numbers of the language.
+//!
+//! By default a number is an `i64`. With the `bigint` feature it is an
+//! arbitrary-precision integer, so arithmetic only overflows when a power is
+//! too large to compute.
+
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+#[cfg(not(feature = "bigint"))]
+pub type Num = i64;
+
+#[cfg(feature = "bigint")]
+pub type Num = BigInt;
+
+/// Parses decimal digits with an optional leading `-`, or returns `None` if
+/// the number is too large to represent.
+pub fn parse(text: &str) -> Option<Num> {
+    text.parse().ok()
+}
+
+/// Converts a count, such as the length of a list.
+#[cfg(not(feature = "bigint"))]
+pub fn from_usize(n: usize) -> Num {
+    n as Num
+}
+
+#[cfg(feature = "bigint")]
+pub fn from_usize(n: usize) -> Num {
+    BigInt::from(n)
+}
+
+/// Converts an `i64`, which every representation can hold. Tests use this to
+/// write numbers the same way whatever the representation.
+#[cfg(all(test, not(feature = "bigint")))]
+pub fn from_i64(n: i64) -> Num {
+    n
+}
+
+#[cfg(all(test, feature = "bigint"))]
+pub fn from_i64(n: i64) -> Num {
+    BigInt::from(n)
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(Some(from_i64(1234)), parse("1234"));
+    assert_eq!(Some(from_i64(-1234)), parse("-1234"));
+    assert_eq!(Some(from_i64(i64::MIN)), parse("-9223372036854775808"));
+    assert_eq!(
+        cfg!(feature = "bigint"),
+        parse("9223372036854775808").is_some()
+    );
+}
@@ -1,158 +1,928 @@
 use ast::BinMathOp::*;
-use ast::Term::{self, *};
-use nom::digit;
-use nom::types::CompleteStr;
-use std::num::ParseIntError;
-
-named!(variable<CompleteStr, Term>, do_parse!(
-    not!(tag!("if")) >>
-    not!(tag!("then")) >>
-    not!(tag!("else")) >>
-    not!(tag!("endif")) >>
-    not!(tag!("true")) >>
-    not!(tag!("false")) >>
-    var_str: re_find!(r"^(?i:[a-z_][a-z0-9_]*)") >>
-    (Var(var_str.to_string()))));
-
-named!(number<CompleteStr, Term>, map_res!(
-    digit, |d: CompleteStr| {
-        let a: Result<Term, ParseIntError> = Ok(NumConst(u64::from_str_radix(*d, 10)?));
-        a
-    }));
-
-named!(boolean<CompleteStr, Term>, map_res!(alt!( tag!("true") | tag!("false")),
-    |s: CompleteStr| {let a: Result<Term, ()> = Ok(BoolConst(*s == "true")); a}));
-
-named!(lambda<CompleteStr, Term>, ws!(do_parse!(
-    tag!(r"\") >>
+use ast::CompareOp::*;
+use ast::LogicOp::*;
+use ast::TermKind::*;
+use ast::{ArrowStyle, CaseArm, FileId, MatchArm, Pattern, Span, Term, TypeAnn};
+use input::line_col;
+use lex::TokenKind::{Keyword, Name, Number, Str, Symbol};
+use lex::{lex, Token, TokenKind, Tokens};
+use nom::{Context, Err, ErrorKind, IResult, Slice};
+use num;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(test)]
+use num::from_i64;
+
+// Codes for committed parse failures. Each one names what the parser
+// expected to find at the position where it gave up; see `expected`.
+const EXPECTED_TERM: u32 = 0;
+const EXPECTED_VARIABLE: u32 = 1;
+const EXPECTED_DOT: u32 = 2;
+const EXPECTED_CLOSE_PAREN: u32 = 3;
+const EXPECTED_THEN: u32 = 4;
+const EXPECTED_ELSE: u32 = 5;
+const EXPECTED_ENDIF: u32 = 6;
+const EXPECTED_SEMICOLON: u32 = 7;
+const EXPECTED_STATEMENT: u32 = 8;
+const EXPECTED_SMALL_NUMBER: u32 = 9;
+const EXPECTED_TYPE: u32 = 10;
+const EXPECTED_COMMENT_END: u32 = 11;
+const EXPECTED_EQUALS: u32 = 12;
+const EXPECTED_IN: u32 = 13;
+const EXPECTED_PROJECTION: u32 = 14;
+const EXPECTED_FIELD: u32 = 15;
+const EXPECTED_NEW_FIELD: u32 = 16;
+const EXPECTED_FIELD_EQUALS: u32 = 17;
+const EXPECTED_COLON: u32 = 18;
+const EXPECTED_CLOSE_BRACE: u32 = 19;
+const EXPECTED_TYPE_NAME: u32 = 20;
+const EXPECTED_DATA_EQUALS: u32 = 21;
+const EXPECTED_CONSTRUCTOR: u32 = 22;
+const EXPECTED_NEW_VARIABLE: u32 = 23;
+const EXPECTED_ARROW: u32 = 24;
+const EXPECTED_OF: u32 = 25;
+const EXPECTED_END: u32 = 26;
+const EXPECTED_PATTERN: u32 = 27;
+const EXPECTED_WITH: u32 = 28;
+const EXPECTED_CLOSE_BRACKET: u32 = 29;
+const EXPECTED_STRING_END: u32 = 30;
+const EXPECTED_ESCAPE: u32 = 31;
+const EXPECTED_DATA_CONSTRUCTOR: u32 = 32;
+
+// Error codes reported for parse failures.
+pub const E_SYNTAX: &str = "E0001";
+pub const E_NUMBER_TOO_LARGE: &str = "E0002";
+
+fn expected(code: u32) -> &'static str {
+    match code {
+        EXPECTED_TERM => "a term",
+        EXPECTED_VARIABLE => "a variable name",
+        EXPECTED_DOT => "`.`",
+        EXPECTED_CLOSE_PAREN => "`)`",
+        EXPECTED_THEN => "`then`",
+        EXPECTED_ELSE => "`else`",
+        EXPECTED_ENDIF => "`endif`",
+        EXPECTED_SEMICOLON => "`;`",
+        EXPECTED_STATEMENT => "a statement",
+        EXPECTED_SMALL_NUMBER => {
+            "a number between -9223372036854775808 and 9223372036854775807"
+        }
+        EXPECTED_TYPE => "a type",
+        EXPECTED_COMMENT_END => "`-}`",
+        EXPECTED_EQUALS => "`=`",
+        EXPECTED_IN => "`in`",
+        EXPECTED_PROJECTION => "a tuple index or a field name",
+        EXPECTED_FIELD => "a field name",
+        EXPECTED_NEW_FIELD => "a field name that is not already used",
+        EXPECTED_FIELD_EQUALS => "`=`",
+        EXPECTED_COLON => "`:`",
+        EXPECTED_CLOSE_BRACE => "`}`",
+        EXPECTED_TYPE_NAME => "a type name",
+        EXPECTED_DATA_EQUALS => "`=`",
+        EXPECTED_CONSTRUCTOR => "a constructor name",
+        EXPECTED_NEW_VARIABLE => "a variable name that is not already used",
+        EXPECTED_ARROW => "`->`",
+        EXPECTED_OF => "`of`",
+        EXPECTED_END => "`end`",
+        EXPECTED_PATTERN => "a pattern",
+        EXPECTED_WITH => "`with`",
+        EXPECTED_CLOSE_BRACKET => "`]`",
+        EXPECTED_STRING_END => "a string that ends with `\"` on the same line",
+        EXPECTED_ESCAPE => "a string whose escapes are `\\n`, `\\t`, `\\r`, `\\\"` or `\\\\`",
+        EXPECTED_DATA_CONSTRUCTOR => "a constructor name",
+        _ => unreachable!(),
+    }
+}
+
+fn help(code: u32) -> Option<&'static str> {
+    match code {
+        EXPECTED_VARIABLE | EXPECTED_DOT => {
+            Some("lambdas are written `\\x. body`, `\\x y. body` or `\\x: Int. body`")
+        }
+        EXPECTED_TYPE => Some(
+            "types are `Int`, `Bool`, `Str`, functions such as `Int -> Bool`, tuples such as \
+             `(Int, Bool)`, records such as `{ x: Int }`, lists such as `List Int` and the \
+             names of data types",
+        ),
+        EXPECTED_THEN | EXPECTED_ELSE | EXPECTED_ENDIF => {
+            Some("conditionals are written `if c then t else f endif`")
+        }
+        EXPECTED_SEMICOLON => Some("every statement must end with `;`"),
+        EXPECTED_EQUALS | EXPECTED_IN => Some(
+            "local definitions are written `let x = value in body` or \
+             `let rec f = (\\x. body) and g = (\\y. body) in body`",
+        ),
+        EXPECTED_COMMENT_END => Some("block comments nest, so every `{-` needs its own `-}`"),
+        EXPECTED_PROJECTION => Some(
+            "the components of a tuple are numbered from 0, as in `pair.0`, and the fields of \
+             a record are named, as in `point.x`",
+        ),
+        EXPECTED_FIELD
+        | EXPECTED_NEW_FIELD
+        | EXPECTED_FIELD_EQUALS
+        | EXPECTED_COLON
+        | EXPECTED_CLOSE_BRACE => Some(
+            "records are written `{ x = 1, flag = true }`, and their types \
+             `{ x: Int, flag: Bool }`",
+        ),
+        EXPECTED_TYPE_NAME | EXPECTED_DATA_EQUALS => {
+            Some("data types are written `data Shape = Circle Int | Rect Int Int;`")
+        }
+        EXPECTED_DATA_CONSTRUCTOR => Some(
+            "data types are written `data Name = Con T ... | ...;`, with each constructor \
+             named before the types of its fields",
+        ),
+        EXPECTED_CONSTRUCTOR | EXPECTED_NEW_VARIABLE | EXPECTED_OF => Some(
+            "case expressions are written `case s of Circle r -> r | Rect w h -> w * h end`",
+        ),
+        EXPECTED_ARROW | EXPECTED_END => Some(
+            "arms are written `Circle r -> body` in a `case` and `0 -> body` in a `match`, \
+             separated by `|` and followed by `end`",
+        ),
+        EXPECTED_PATTERN | EXPECTED_WITH => Some(
+            "match expressions are written `match n with | 0 -> a | _ -> b end`, where a \
+             pattern is a number, `true`, `false`, `_`, a variable name, `[]`, `[p, q]` or \
+             `p :: q`",
+        ),
+        EXPECTED_CLOSE_BRACKET => Some("lists are written `[1, 2, 3]`, `[]` or `head :: tail`"),
+        EXPECTED_STRING_END | EXPECTED_ESCAPE => Some(
+            "strings are written `\"hello\"`; a line break is written `\\n`, a tab `\\t`, a \
+             quote `\\\"` and a backslash `\\\\`",
+        ),
+        _ => None,
+    }
+}
+
+/// Commits to the current alternative: if `$submac` fails, the failure is
+/// tagged with `$code` and is not backtracked over by an enclosing `alt!` or
+/// `many0!`. Failures that were already committed pass through unchanged.
+macro_rules! expect (
+    ($i:expr, $code:expr, $submac:ident!( $($args:tt)* )) => (
+        match $submac!($i, $($args)*) {
+            ::std::result::Result::Err(::nom::Err::Error(_)) => ::std::result::Result::Err(
+                ::nom::Err::Failure(::nom::Context::Code($i, ::nom::ErrorKind::Custom($code))),
+            ),
+            res => res,
+        }
+    );
+    ($i:expr, $code:expr, $f:expr) => (
+        expect!($i, $code, call!($f))
+    );
+);
+
+/// Takes the next token if it is of kind `kind`, and has the text `text` if
+/// that is given.
+fn token<'a>(
+    input: Tokens<'a>,
+    kind: TokenKind,
+    text: Option<&str>,
+) -> IResult<Tokens<'a>, Token<'a>> {
+    match input.tokens.first() {
+        Some(token) if token.kind == kind && text.is_none_or(|text| token.text == text) => {
+            Ok((input.slice(1..), *token))
+        }
+        _ => Err(Err::Error(Context::Code(input, ErrorKind::Tag))),
+    }
+}
+
+/// Matches the keyword `$word`.
+macro_rules! keyword (
+    ($i:expr, $word:expr) => (
+        call!($i, token, Keyword, Some($word))
+    );
+);
+
+/// Matches the operator or punctuation `$symbol`.
+macro_rules! symbol (
+    ($i:expr, $symbol:expr) => (
+        call!($i, token, Symbol, Some($symbol))
+    );
+);
+
+/// A syntax error, located at the point where the parser gave up.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ParseError {
+    pub code: &'static str,
+    /// The span of whatever was found where something else was expected.
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    pub expected: &'static str,
+    pub found: String,
+    pub help: Option<&'static str>,
+}
+
+impl ParseError {
+    /// An error at the start of `input`, which is the end of `contents` if
+    /// no tokens are left. A number that is too large is found together with
+    /// its minus sign, if it has one.
+    fn new(contents: &str, input: Tokens, expected_code: u32) -> ParseError {
+        let (span, found) = match (expected_code, input.tokens) {
+            (EXPECTED_SMALL_NUMBER, [minus, digits, ..]) if minus.kind == Symbol => (
+                minus.span.to(digits.span),
+                format!("`{}{}`", minus.text, digits.text),
+            ),
+            _ => (
+                input.span(),
+                match input.tokens.first() {
+                    Some(token) => format!("`{}`", token.text),
+                    None => "end of input".to_string(),
+                },
+            ),
+        };
+        let (line, column) = line_col(contents, span.start);
+        ParseError {
+            code: match expected_code {
+                EXPECTED_SMALL_NUMBER => E_NUMBER_TOO_LARGE,
+                _ => E_SYNTAX,
+            },
+            span,
+            line,
+            column,
+            expected: expected(expected_code),
+            found,
+            help: help(expected_code),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+named!(variable<Tokens, Term>, map!(call!(token, Name, None),
+    |name: Token| Term::new(Var(name.text.to_string()), name.span)));
+
+fn number(input: Tokens) -> IResult<Tokens, Term> {
+    let (rest, digits) = token(input, Number, None)?;
+    number_const(input, rest, digits.text.to_string(), digits.span)
+}
+
+// A minus sign directly in front of digits is part of the number, so that
+// the smallest number can be written. Unless the number is raised to a
+// power: `-2 ^ 2` is `-(2 ^ 2)`.
+fn negative_number(input: Tokens) -> IResult<Tokens, Term> {
+    let (rest, (minus, digits)) = verify!(
+        input,
+        tuple!(symbol!("-"), call!(token, Number, None)),
+        |(minus, digits): (Token, Token)| minus.span.end == digits.span.start
+    )?;
+    let (rest, _) = not!(rest, symbol!("^"))?;
+    number_const(input, rest, format!("-{}", digits.text), minus.span.to(digits.span))
+}
+
+/// The number `text`, spanning `span`, followed by `rest`. Fails, without
+/// backtracking, at the start of `input` if `text` is too large for a
+/// number.
+fn number_const<'a>(
+    input: Tokens<'a>,
+    rest: Tokens<'a>,
+    text: String,
+    span: Span,
+) -> IResult<Tokens<'a>, Term> {
+    match num::parse(&text) {
+        Some(n) => Ok((rest, Term::new(NumConst(n), span))),
+        None => Err(Err::Failure(Context::Code(
+            input,
+            ErrorKind::Custom(EXPECTED_SMALL_NUMBER),
+        ))),
+    }
+}
+
+/// A string literal. Fails, without backtracking, if it is not closed or
+/// has an escape that does not stand for anything.
+fn string(input: Tokens) -> IResult<Tokens, Term> {
+    let (rest, literal) = token(input, Str, None)?;
+    let fail = |code| Err(Err::Failure(Context::Code(input, ErrorKind::Custom(code))));
+    let mut value = String::new();
+    let mut chars = literal.text[1..].chars();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok((rest, Term::new(StrConst(value), literal.span))),
+            Some('\\') => value.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some(c @ '"') | Some(c @ '\\') => c,
+                _ => return fail(EXPECTED_ESCAPE),
+            }),
+            Some(c) => value.push(c),
+            None => return fail(EXPECTED_STRING_END),
+        }
+    }
+}
+
+named!(boolean<Tokens, Term>, map!(alt!(keyword!("true") | keyword!("false")),
+    |b: Token| Term::new(BoolConst(b.text == "true"), b.span)));
+
+named!(type_name<Tokens, TypeAnn>, alt!(
+    map!(call!(token, Name, Some("Int")), |_| TypeAnn::Int)
+    | map!(call!(token, Name, Some("Bool")), |_| TypeAnn::Bool)
+    | map!(call!(token, Name, Some("Str")), |_| TypeAnn::Str)
+    | do_parse!(
+        call!(token, Name, Some("List")) >>
+        element: expect!(EXPECTED_TYPE, type_name) >>
+        (TypeAnn::List(Box::new(element))))
+    | map!(call!(token, Name, None), |name| TypeAnn::Named(name.text.to_string()))
+    | do_parse!(
+        symbol!("(") >>
+        first: expect!(EXPECTED_TYPE, type_ann) >>
+        rest: many0!(preceded!(symbol!(","), expect!(EXPECTED_TYPE, type_ann))) >>
+        expect!(EXPECTED_CLOSE_PAREN, symbol!(")")) >>
+        (if rest.is_empty() {
+            first
+        } else {
+            let mut components = vec![first];
+            components.extend(rest);
+            TypeAnn::Tuple(components)
+        }))
+    | map!(
+        delimited!(
+            symbol!("{"),
+            call!(fields, ":", EXPECTED_COLON, type_ann, EXPECTED_TYPE),
+            expect!(EXPECTED_CLOSE_BRACE, symbol!("}"))),
+        TypeAnn::Record)));
+
+named!(arrow<Tokens, Token>, alt!(symbol!("->") | symbol!("→")));
+
+// Function types associate to the right: `Int -> Int -> Int` is
+// `Int -> (Int -> Int)`.
+named!(type_ann<Tokens, TypeAnn>, do_parse!(
+    from: type_name >>
+    to: opt!(preceded!(arrow, expect!(EXPECTED_TYPE, type_ann))) >>
+    (match to {
+        Some(to) => TypeAnn::Arrow(Box::new(from), Box::new(to)),
+        None => from,
+    })));
+
+named!(lambda_param<Tokens, (Term, Option<TypeAnn>)>, do_parse!(
     var: variable >>
-    tag!(".") >>
-    term: term >>
+    var_type: opt!(preceded!(symbol!(":"), expect!(EXPECTED_TYPE, type_ann))) >>
+    (var, var_type)));
+
+// `\x y. body` is short for `\x. \y. body`, and `λ` may be written for `\`.
+// The body extends as far to the right as possible.
+named!(lambda<Tokens, Term>, do_parse!(
+    backslash: alt!(symbol!("\\") | symbol!("λ")) >>
+    first: expect!(EXPECTED_VARIABLE, lambda_param) >>
+    rest: many0!(lambda_param) >>
+    expect!(EXPECTED_DOT, symbol!(".")) >>
+    term: expect!(EXPECTED_TERM, term) >>
     ({
-        match var {
-            Var(var) => Lambda { var_name: var, expr: Box::new(term) },
-            _ => unreachable!(),
+        let mut params = vec![first];
+        params.extend(rest);
+        let start = backslash.span;
+        params.into_iter().enumerate().rev().fold(term, |body, (i, (var, var_type))| {
+            let span = if i == 0 { start } else { var.span }.to(body.span);
+            match var.kind {
+                Var(var) => Term::new(
+                    Lambda { var_name: var, var_type, expr: Box::new(body) },
+                    span),
+                _ => unreachable!(),
+            }
+        })
+    })));
+
+// A parenthesized term, or a tuple if there are commas.
+named!(parenthesized<Tokens, Term>, do_parse!(
+    open: symbol!("(") >>
+    first: expect!(EXPECTED_TERM, term) >>
+    rest: many0!(preceded!(symbol!(","), expect!(EXPECTED_TERM, term))) >>
+    close: expect!(EXPECTED_CLOSE_PAREN, symbol!(")")) >>
+    (if rest.is_empty() {
+        Term { span: open.span.to(close.span), ..first }
+    } else {
+        let mut components = vec![first];
+        components.extend(rest);
+        Term::new(Tuple(components), open.span.to(close.span))
+    })));
+
+/// The fields of a record or of a record type: `name = value, ...` with `=`
+/// written as `equals`, where each value is parsed by `value`. The list may
+/// be empty, but no name may appear twice.
+fn fields<'a, O>(
+    input: Tokens<'a>,
+    equals: &'static str,
+    expected_equals: u32,
+    value: fn(Tokens<'a>) -> IResult<Tokens<'a>, O>,
+    expected_value: u32,
+) -> IResult<Tokens<'a>, Vec<(String, O)>> {
+    let mut fields: Vec<(String, O)> = Vec::new();
+    let mut input = input;
+    loop {
+        let (rest, name) = match token(input, Name, None) {
+            Ok(found) => found,
+            Err(_) if fields.is_empty() => return Ok((input, fields)),
+            Err(_) => {
+                return Err(Err::Failure(Context::Code(
+                    input,
+                    ErrorKind::Custom(EXPECTED_FIELD),
+                )))
+            }
+        };
+        if fields.iter().any(|(field, _)| field == name.text) {
+            return Err(Err::Failure(Context::Code(
+                input,
+                ErrorKind::Custom(EXPECTED_NEW_FIELD),
+            )));
         }
-    }))));
+        let (rest, _) = expect!(rest, expected_equals, symbol!(equals))?;
+        let (rest, value) = expect!(rest, expected_value, value)?;
+        fields.push((name.text.to_string(), value));
+        match symbol!(rest, ",") {
+            Ok((rest, _)) => input = rest,
+            Err(_) => return Ok((rest, fields)),
+        }
+    }
+}
+
+named!(record<Tokens, Term>, do_parse!(
+    open: symbol!("{") >>
+    fields: call!(fields, "=", EXPECTED_FIELD_EQUALS, term, EXPECTED_TERM) >>
+    close: expect!(EXPECTED_CLOSE_BRACE, symbol!("}")) >>
+    (Term::new(Record(fields), open.span.to(close.span)))));
 
-named!(terminal<CompleteStr, Term>, alt!(
-    variable | number | boolean | delimited!(char!('('), alt!(lambda | term), char!(')'))));
+named!(list<Tokens, Term>, do_parse!(
+    open: symbol!("[") >>
+    elements: alt!(
+        map!(peek!(symbol!("]")), |_| Vec::new())
+        | separated_nonempty_list!(symbol!(","), expect!(EXPECTED_TERM, term))) >>
+    close: expect!(EXPECTED_CLOSE_BRACKET, symbol!("]")) >>
+    (Term::new(List(elements), open.span.to(close.span)))));
 
-named!(application<CompleteStr, Term>, ws!(do_parse!(
+named!(terminal<Tokens, Term>,
+    alt!(variable | number | boolean | string | parenthesized | record | list));
+
+// Projections bind more tightly than application: `f p.0` is `f (p.0)`.
+// An index too large for any tuple is left for the type checker to reject.
+named!(projection<Tokens, Term>, do_parse!(
     first: terminal >>
-    rest: many0!(ws!(terminal)) >>
+    rest: many0!(preceded!(
+        symbol!("."),
+        expect!(EXPECTED_PROJECTION, alt!(call!(token, Number, None) | call!(token, Name, None))))) >>
+    (rest.into_iter().fold(first, |acc, selector| {
+        let span = acc.span.to(selector.span);
+        let kind = match selector.kind {
+            Number => Project {
+                tuple: Box::new(acc),
+                index: selector.text.parse().unwrap_or(usize::MAX),
+            },
+            _ => Field { record: Box::new(acc), field: selector.text.to_string() },
+        };
+        Term::new(kind, span)
+    }))));
+
+named!(application<Tokens, Term>, do_parse!(
+    first: projection >>
+    rest: many0!(projection) >>
     (rest.into_iter().fold(first, |acc, i| {
-        Apply { var_term: Box::new(i), function: Box::new(acc) }
-    })))));
+        let span = acc.span.to(i.span);
+        Term::new(Apply { var_term: Box::new(i), function: Box::new(acc) }, span)
+    }))));
+
+/// An arm of a `case`, `Constructor x y -> body`. No variable other than `_`
+/// may appear twice in the pattern.
+fn case_arm(input: Tokens) -> IResult<Tokens, CaseArm> {
+    let (mut input, constructor) =
+        expect!(input, EXPECTED_CONSTRUCTOR, call!(token, Name, None))?;
+    let mut vars: Vec<String> = Vec::new();
+    let mut span = constructor.span;
+    while let Ok((rest, var)) = token(input, Name, None) {
+        if var.text != "_" && vars.iter().any(|v| v == var.text) {
+            return Err(Err::Failure(Context::Code(
+                input,
+                ErrorKind::Custom(EXPECTED_NEW_VARIABLE),
+            )));
+        }
+        vars.push(var.text.to_string());
+        span = span.to(var.span);
+        input = rest;
+    }
+    let (input, _) = expect!(input, EXPECTED_ARROW, arrow)?;
+    let (input, body) = expect!(input, EXPECTED_TERM, term)?;
+    let arm = CaseArm {
+        constructor: constructor.text.to_string(),
+        vars,
+        body,
+        span,
+    };
+    Ok((input, arm))
+}
 
-named!(multiplicand<CompleteStr, Term>, alt!(
+named!(pattern_atom<Tokens, (Pattern, Span)>, alt!(
+    map!(alt!(number | negative_number | boolean), |literal: Term| match literal.kind {
+        NumConst(n) => (Pattern::Num(n), literal.span),
+        BoolConst(b) => (Pattern::Bool(b), literal.span),
+        _ => unreachable!(),
+    })
+    | map!(call!(token, Name, None), |name: Token| match name.text {
+        "_" => (Pattern::Wildcard, name.span),
+        _ => (Pattern::Var(name.text.to_string()), name.span),
+    })
+    | do_parse!(
+        open: symbol!("[") >>
+        elements: alt!(
+            map!(peek!(symbol!("]")), |_| Vec::new())
+            | separated_nonempty_list!(symbol!(","), expect!(EXPECTED_PATTERN, pattern))) >>
+        close: expect!(EXPECTED_CLOSE_BRACKET, symbol!("]")) >>
+        (elements.into_iter().rev().fold(Pattern::Nil, |tail, (head, _)| {
+            Pattern::Cons(Box::new(head), Box::new(tail))
+        }), open.span.to(close.span)))
+    | do_parse!(
+        open: symbol!("(") >>
+        inner: expect!(EXPECTED_PATTERN, pattern) >>
+        close: expect!(EXPECTED_CLOSE_PAREN, symbol!(")")) >>
+        (inner.0, open.span.to(close.span)))));
+
+// As in terms, `::` associates to the right.
+named!(pattern<Tokens, (Pattern, Span)>, do_parse!(
+    head: pattern_atom >>
+    tail: opt!(preceded!(symbol!("::"), expect!(EXPECTED_PATTERN, pattern))) >>
+    (match tail {
+        None => head,
+        Some((tail, span)) => (Pattern::Cons(Box::new(head.0), Box::new(tail)), head.1.to(span)),
+    })));
+
+named!(match_arm<Tokens, MatchArm>, do_parse!(
+    pattern: expect!(EXPECTED_PATTERN, pattern) >>
+    expect!(EXPECTED_ARROW, arrow) >>
+    body: expect!(EXPECTED_TERM, term) >>
+    (MatchArm { pattern: pattern.0, body, span: pattern.1 })));
+
+named!(multiplicand<Tokens, Term>, alt!(
     do_parse!(
-        tag!("if") >>
-        c: term >>
-        tag!("then") >>
-        t: term >>
-        tag!("else") >>
-        f: term >>
-        tag!("endif") >>
-        (IfStmt { test: Box::new(c), then_body: Box::new(t), else_body: Box::new(f) }))
-    | application));
-
-named!(addend<CompleteStr, Term>, ws!(do_parse!(
+        start: keyword!("if") >>
+        c: expect!(EXPECTED_TERM, term) >>
+        expect!(EXPECTED_THEN, keyword!("then")) >>
+        t: expect!(EXPECTED_TERM, term) >>
+        expect!(EXPECTED_ELSE, keyword!("else")) >>
+        f: expect!(EXPECTED_TERM, term) >>
+        end: expect!(EXPECTED_ENDIF, keyword!("endif")) >>
+        (Term::new(
+            IfStmt { test: Box::new(c), then_body: Box::new(t), else_body: Box::new(f) },
+            start.span.to(end.span))))
+    | do_parse!(
+        start: keyword!("let") >>
+        keyword!("rec") >>
+        bindings: separated_nonempty_list!(keyword!("and"), call!(binding, "=")) >>
+        expect!(EXPECTED_IN, keyword!("in")) >>
+        body: expect!(EXPECTED_TERM, term) >>
+        ({
+            let span = start.span.to(body.span);
+            Term::new(LetRec { bindings, body: Box::new(body) }, span)
+        }))
+    | do_parse!(
+        start: keyword!("let") >>
+        var: expect!(EXPECTED_VARIABLE, variable) >>
+        expect!(EXPECTED_EQUALS, symbol!("=")) >>
+        value: expect!(EXPECTED_TERM, term) >>
+        expect!(EXPECTED_IN, keyword!("in")) >>
+        body: expect!(EXPECTED_TERM, term) >>
+        ({
+            let span = start.span.to(body.span);
+            match var.kind {
+                Var(name) => Term::new(
+                    Let { name, value: Box::new(value), body: Box::new(body) },
+                    span),
+                _ => unreachable!(),
+            }
+        }))
+    // Each arm's body extends as far to the right as possible, up to the next
+    // `|` or the `end`.
+    | do_parse!(
+        start: keyword!("case") >>
+        scrutinee: expect!(EXPECTED_TERM, term) >>
+        expect!(EXPECTED_OF, keyword!("of")) >>
+        opt!(symbol!("|")) >>
+        arms: separated_nonempty_list!(symbol!("|"), case_arm) >>
+        end: expect!(EXPECTED_END, keyword!("end")) >>
+        (Term::new(Case { scrutinee: Box::new(scrutinee), arms }, start.span.to(end.span))))
+    | do_parse!(
+        start: keyword!("match") >>
+        scrutinee: expect!(EXPECTED_TERM, term) >>
+        expect!(EXPECTED_WITH, keyword!("with")) >>
+        opt!(symbol!("|")) >>
+        arms: separated_nonempty_list!(symbol!("|"), match_arm) >>
+        end: expect!(EXPECTED_END, keyword!("end")) >>
+        (Term::new(Match { scrutinee: Box::new(scrutinee), arms }, start.span.to(end.span))))
+    | lambda
+    | negative_number
+    | do_parse!(
+        bang: symbol!("!") >>
+        operand: expect!(EXPECTED_TERM, multiplicand) >>
+        ({
+            let span = bang.span.to(operand.span);
+            Term::new(Not(Box::new(operand)), span)
+        }))
+    | do_parse!(
+        minus: symbol!("-") >>
+        operand: expect!(EXPECTED_TERM, multiplicand) >>
+        ({
+            let span = minus.span.to(operand.span);
+            Term::new(Negate(Box::new(operand)), span)
+        }))
+    | power));
+
+// `^` binds more tightly than a minus sign in front of it, and associates to
+// the right: `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`.
+named!(power<Tokens, Term>, do_parse!(
+    base: application >>
+    exponent: opt!(preceded!(symbol!("^"), expect!(EXPECTED_TERM, multiplicand))) >>
+    (match exponent {
+        None => base,
+        Some(exponent) => {
+            let span = base.span.to(exponent.span);
+            Term::new(MathOp { opr: Power, t1: Box::new(base), t2: Box::new(exponent) }, span)
+        }
+    })));
+
+named!(addend<Tokens, Term>, do_parse!(
     first: multiplicand >>
-    rest: many0!(ws!(tuple!(one_of!("*/"), multiplicand))) >>
+    rest: many0!(tuple!(
+        alt!(symbol!("*") | symbol!("/") | symbol!("%")),
+        expect!(EXPECTED_TERM, multiplicand))) >>
     (rest.into_iter().fold(first, |acc, (op, i)| {
-        let op = match op {
-            '*' => Multiply,
-            '/' => Divide,
+        let op = match op.text {
+            "*" => Multiply,
+            "/" => Divide,
+            "%" => Modulo,
             _ => unreachable!(),
         };
-        MathOp { opr: op, t1: Box::new(acc), t2: Box::new(i) }
-    })))));
+        let span = acc.span.to(i.span);
+        Term::new(MathOp { opr: op, t1: Box::new(acc), t2: Box::new(i) }, span)
+    }))));
 
-named!(equalend<CompleteStr, Term>, ws!(do_parse!(
+// `++` binds like `+` and `-`, so `"n = " ++ show n` needs no brackets.
+named!(equalend<Tokens, Term>, do_parse!(
     first: addend >>
-    rest: many0!(ws!(tuple!(one_of!("+-"), addend))) >>
+    rest: many0!(tuple!(
+        alt!(symbol!("+") | symbol!("-") | symbol!("++")),
+        expect!(EXPECTED_TERM, addend))) >>
     (rest.into_iter().fold(first, |acc, (op, i)| {
-        let op = match op {
-            '+' => Add,
-            '-' => Minus,
+        let span = acc.span.to(i.span);
+        let (t1, t2) = (Box::new(acc), Box::new(i));
+        let kind = match op.text {
+            "+" => MathOp { opr: Add, t1, t2 },
+            "-" => MathOp { opr: Minus, t1, t2 },
+            "++" => Concat { left_side: t1, right_side: t2 },
             _ => unreachable!(),
         };
-        MathOp { opr: op, t1: Box::new(acc), t2: Box::new(i) }
-    })))));
+        Term::new(kind, span)
+    }))));
 
-named!(term<CompleteStr, Term>, ws!(do_parse!(
-    left: equalend >>
-    right: opt!(ws!(tuple!(alt!(tag!("==")|tag!("!=")), equalend))) >>
+// `::` associates to the right: `1 :: 2 :: []` is `1 :: (2 :: [])`.
+named!(cons<Tokens, Term>, do_parse!(
+    head: equalend >>
+    tail: opt!(preceded!(symbol!("::"), expect!(EXPECTED_TERM, cons))) >>
+    (match tail {
+        None => head,
+        Some(tail) => {
+            let span = head.span.to(tail.span);
+            Term::new(Cons { head: Box::new(head), tail: Box::new(tail) }, span)
+        }
+    })));
+
+// Comparisons do not chain: `a < b < c` is a syntax error.
+named!(comparison<Tokens, Term>, do_parse!(
+    left: cons >>
+    right: opt!(tuple!(
+        alt!(symbol!("==") | symbol!("!=") | symbol!("<=") | symbol!(">=") | symbol!("<")
+            | symbol!(">")),
+        expect!(EXPECTED_TERM, cons))) >>
     (match right {
         None => left,
         Some((op, right)) => {
-            match *op {
-                "==" => Equals { left_side: Box::new(left), right_side: Box::new(right) },
-                "!=" => NotEquals { left_side: Box::new(left), right_side: Box::new(right) },
+            let span = left.span.to(right.span);
+            let (left_side, right_side) = (Box::new(left), Box::new(right));
+            let kind = match op.text {
+                "==" => Equals { left_side, right_side },
+                "!=" => NotEquals { left_side, right_side },
+                "<" => Compare { opr: Less, left_side, right_side },
+                "<=" => Compare { opr: LessEq, left_side, right_side },
+                ">" => Compare { opr: Greater, left_side, right_side },
+                ">=" => Compare { opr: GreaterEq, left_side, right_side },
                 _ => unreachable!(),
-            }
+            };
+            Term::new(kind, span)
         }
+    })));
+
+named!(conjunction<Tokens, Term>, do_parse!(
+    first: comparison >>
+    rest: many0!(preceded!(symbol!("&&"), expect!(EXPECTED_TERM, comparison))) >>
+    (rest.into_iter().fold(first, |acc, i| {
+        let span = acc.span.to(i.span);
+        Term::new(Logic { opr: And, left_side: Box::new(acc), right_side: Box::new(i) }, span)
     }))));
 
-named!(assignment<CompleteStr, Term>, ws!(do_parse!(
-    var_name: variable >>
-    tag!(":=") >>
-    expr: term >>
-    (match var_name {
-        Var(var_name) => Assignm { var_name, expr: Box::new(expr) },
-        _ => unreachable!(),
+named!(term<Tokens, Term>, do_parse!(
+    first: conjunction >>
+    rest: many0!(preceded!(symbol!("||"), expect!(EXPECTED_TERM, conjunction))) >>
+    (rest.into_iter().fold(first, |acc, i| {
+        let span = acc.span.to(i.span);
+        Term::new(Logic { opr: Or, left_side: Box::new(acc), right_side: Box::new(i) }, span)
     }))));
 
-named!(_file<CompleteStr, Vec<Term>>, ws!(do_parse!(
-    list: ws!(separated_list!(tag!(";"), alt!(assignment | term))) >>
-    tag!(";") >>
-    eof!() >>
-    (list))));
+named!(assignment<Tokens, Term>, do_parse!(
+    var_name: variable >>
+    symbol!(":=") >>
+    expr: expect!(EXPECTED_TERM, term) >>
+    ({
+        let span = var_name.span.to(expr.span);
+        match var_name.kind {
+            Var(var_name) => Term::new(Assignm { var_name, expr: Box::new(expr) }, span),
+            _ => unreachable!(),
+        }
+    })));
+
+/// One definition of a recursive group, `name = value` with `=` written as
+/// `equals`.
+fn binding<'a>(input: Tokens<'a>, equals: &'static str) -> IResult<Tokens<'a>, (String, Term)> {
+    do_parse!(input,
+        var: expect!(EXPECTED_VARIABLE, variable) >>
+        expect!(EXPECTED_EQUALS, symbol!(equals)) >>
+        value: expect!(EXPECTED_TERM, term) >>
+        (match var.kind {
+            Var(name) => (name, value),
+            _ => unreachable!(),
+        }))
+}
+
+// `rec f := ... and g := ...` assigns each function of the group in turn.
+// At the top level, the caller groups assignments that refer to each other,
+// so `rec` and `and` only say that they are meant to.
+named!(rec_assignment<Tokens, Vec<Term>>, do_parse!(
+    first: pair!(keyword!("rec"), call!(binding, ":=")) >>
+    rest: many0!(pair!(keyword!("and"), call!(binding, ":="))) >>
+    (std::iter::once(first).chain(rest).map(|(keyword, (var_name, expr))| {
+        let span = keyword.span.to(expr.span);
+        Term::new(Assignm { var_name, expr: Box::new(expr) }, span)
+    }).collect())));
+
+named!(constructor<Tokens, (String, Vec<TypeAnn>)>, do_parse!(
+    name: expect!(EXPECTED_DATA_CONSTRUCTOR, call!(token, Name, None)) >>
+    fields: many0!(type_name) >>
+    (name.text.to_string(), fields)));
+
+/// `data Name = Con Type ... | ...`, where each field type is a name or is
+/// enclosed in brackets.
+fn data(input: Tokens) -> IResult<Tokens, Term> {
+    let (rest, (start, name, constructors)) = do_parse!(input,
+        start: keyword!("data") >>
+        name: expect!(EXPECTED_TYPE_NAME, call!(token, Name, None)) >>
+        expect!(EXPECTED_DATA_EQUALS, symbol!("=")) >>
+        opt!(symbol!("|")) >>
+        constructors: separated_nonempty_list!(symbol!("|"), constructor) >>
+        (start, name, constructors))?;
+    let last = input.tokens[input.tokens.len() - rest.tokens.len() - 1];
+    let kind = Data {
+        name: name.text.to_string(),
+        constructors,
+    };
+    Ok((rest, Term::new(kind, start.span.to(last.span))))
+}
+
+named!(statement<Tokens, Vec<Term>>, terminated!(
+    alt!(rec_assignment | map!(alt!(data | assignment | term), |t| vec![t])),
+    expect!(EXPECTED_SEMICOLON, symbol!(";"))));
+
+named!(_file<Tokens, Vec<Term>>, do_parse!(
+    first: expect!(EXPECTED_STATEMENT, statement) >>
+    rest: many0!(statement) >>
+    expect!(EXPECTED_STATEMENT, eof!()) >>
+    ({
+        let mut list = first;
+        list.extend(rest.into_iter().flatten());
+        list
+    })));
+
+/// The empty span at the end of `contents`.
+fn end_span(file: FileId, contents: &str) -> Span {
+    Span {
+        file,
+        start: contents.len(),
+        end: contents.len(),
+    }
+}
+
+pub fn parse_file(file: FileId, contents: &str) -> Result<Vec<Term>, ParseError> {
+    let end = end_span(file, contents);
+    let tokens = match lex(file, contents) {
+        Ok(tokens) => tokens,
+        // The input runs out inside the comment, which is reported where
+        // the comment opens.
+        Err(unclosed) => {
+            return Err(ParseError::new(
+                contents,
+                Tokens::new(&[], unclosed.span),
+                EXPECTED_COMMENT_END,
+            ))
+        }
+    };
+    match _file(Tokens::new(&tokens, end)) {
+        Ok((_, terms)) => Ok(terms),
+        Err(Err::Error(Context::Code(input, kind)))
+        | Err(Err::Failure(Context::Code(input, kind))) => {
+            let expected_code = match kind {
+                ErrorKind::Custom(code) => code,
+                _ => EXPECTED_STATEMENT,
+            };
+            Err(ParseError::new(contents, input, expected_code))
+        }
+        Err(Err::Incomplete(_)) => Err(ParseError::new(
+            contents,
+            Tokens::new(&[], end),
+            EXPECTED_STATEMENT,
+        )),
+    }
+}
+
+/// The arrow that `contents` is written with: `→` if it writes any arrow
+/// that way, and `->` otherwise.
+pub fn arrow_style(file: FileId, contents: &str) -> ArrowStyle {
+    let tokens = lex(file, contents).unwrap_or_default();
+    if tokens.iter().any(|token| token.kind == Symbol && token.text == "→") {
+        ArrowStyle::Unicode
+    } else {
+        ArrowStyle::Ascii
+    }
+}
 
-pub fn parse_file(contents: &str) -> Result<Vec<Term>, &'static str> {
-    _file(CompleteStr(contents))
-        .map(|i| i.1)
-        .or(Err("Parse failed"))
+/// Runs `parser` on the tokens of `s`, with the remaining tokens replaced by
+/// the source text they start at so that tests can compare against plain
+/// strings.
+#[cfg(test)]
+fn parse_str<O>(
+    parser: for<'t> fn(Tokens<'t>) -> IResult<Tokens<'t>, O>,
+    s: &str,
+) -> IResult<&str, O> {
+    let tokens = lex(0, s).unwrap();
+    let rest = |input: Tokens| &s[input.span().start..];
+    match parser(Tokens::new(&tokens, end_span(0, s))) {
+        Ok((input, o)) => Ok((rest(input), o)),
+        Err(Err::Error(Context::Code(input, kind))) => {
+            Err(Err::Error(Context::Code(rest(input), kind)))
+        }
+        Err(Err::Failure(Context::Code(input, kind))) => {
+            Err(Err::Failure(Context::Code(rest(input), kind)))
+        }
+        Err(Err::Incomplete(needed)) => Err(Err::Incomplete(needed)),
+    }
 }
 
 #[test]
 fn test_variable() {
-    use nom::{
-        Context::Code,
-        Err::Error,
-        ErrorKind::{Not, RegexpFind},
-    };
+    use nom::{Context::Code, Err::Error, ErrorKind::Tag};
 
     assert_eq!(
-        variable(CompleteStr("_things{}")),
-        Ok((CompleteStr("{}"), Var("_things".to_string())))
+        parse_str(variable, "_things{}"),
+        Ok(("{}", Var("_things".to_string()).into()))
+    );
+    assert_eq!(
+        parse_str(variable, "_things _stuff"),
+        Ok(("_stuff", Var("_things".to_string()).into()))
     );
     assert_eq!(
-        variable(CompleteStr("_things _stuff")),
-        Ok((CompleteStr(" _stuff"), Var("_things".to_string())))
+        parse_str(variable, "1_things::/"),
+        Err(Error(Code("1_things::/", Tag)))
     );
+    assert_eq!(parse_str(variable, "endif"), Err(Error(Code("endif", Tag))));
+    // Keywords only count as whole words.
     assert_eq!(
-        variable(CompleteStr("1_things::/")),
-        Err(Error(Code(CompleteStr("1_things::/"), RegexpFind)))
+        parse_str(variable, "iffy + 1"),
+        Ok(("+ 1", Var("iffy".to_string()).into()))
     );
     assert_eq!(
-        variable(CompleteStr("endif")),
-        Err(Error(Code(CompleteStr("endif"), Not)))
+        parse_str(variable, "trueValue"),
+        Ok(("", Var("trueValue".to_string()).into()))
     );
+    assert_eq!(
+        parse_str(variable, "αβ_1′"),
+        Ok(("′", Var("αβ_1".to_string()).into()))
+    );
+    assert_eq!(parse_str(variable, "λx"), Err(Error(Code("λx", Tag))));
 }
 
 #[test]
 fn test_number() {
-    use nom::{Context::Code, Err::Error, ErrorKind::Digit};
+    use nom::{Context::Code, Err::Error, ErrorKind::Tag};
 
     assert_eq!(
-        number(CompleteStr("13potato")),
-        Ok((CompleteStr("potato"), NumConst(13)))
+        parse_str(number, "13potato"),
+        Ok(("potato", NumConst(from_i64(13)).into()))
     );
     assert_eq!(
-        number(CompleteStr("potato13")),
-        Err(Error(Code(CompleteStr("potato13"), Digit)))
+        parse_str(number, "potato13"),
+        Err(Error(Code("potato13", Tag)))
     );
 }
 
@@ -161,33 +931,197 @@ fn test_boolean() {
     use nom::{Context::Code, Err::Error, ErrorKind::Alt};
 
     assert_eq!(
-        boolean(CompleteStr("truefalse")),
-        Ok((CompleteStr("false"), BoolConst(true)))
+        parse_str(boolean, "true false"),
+        Ok(("false", BoolConst(true).into()))
+    );
+    assert_eq!(
+        parse_str(boolean, "false)"),
+        Ok((")", BoolConst(false).into()))
+    );
+    assert_eq!(
+        parse_str(boolean, "falsefalse"),
+        Err(Error(Code("falsefalse", Alt)))
+    );
+    assert_eq!(
+        parse_str(boolean, "falsfalse"),
+        Err(Error(Code("falsfalse", Alt)))
     );
+}
+
+#[test]
+fn test_keyword_prefixes() {
+    // Names that start with a keyword are single name tokens.
+    let (_, t) = parse_str(
+        term,
+        "if iffy then thenable else elsewhere endif && trueValue || false_case",
+    )
+    .unwrap();
+    let var = |name: &str| -> Box<Term> { Box::new(Var(name.to_string()).into()) };
     assert_eq!(
-        boolean(CompleteStr("falsefalse")),
-        Ok((CompleteStr("false"), BoolConst(false)))
+        t,
+        Logic {
+            opr: Or,
+            left_side: Box::new(
+                Logic {
+                    opr: And,
+                    left_side: Box::new(
+                        IfStmt {
+                            test: var("iffy"),
+                            then_body: var("thenable"),
+                            else_body: var("elsewhere"),
+                        }
+                        .into()
+                    ),
+                    right_side: var("trueValue"),
+                }
+                .into()
+            ),
+            right_side: var("false_case"),
+        }
+        .into()
     );
     assert_eq!(
-        boolean(CompleteStr("falsfalse")),
-        Err(Error(Code(CompleteStr("falsfalse"), Alt)))
+        parse_file(0, "x := 1 # 2;").unwrap_err().found,
+        "`#`".to_string()
     );
 }
 
 #[test]
 fn test_lambda() {
     assert_eq!(
-        lambda(CompleteStr(r"\ x . x + 1")),
+        parse_str(lambda, r"\ x : Int . x + 1"),
         Ok((
-            CompleteStr(""),
+            "",
             Lambda {
                 var_name: "x".to_string(),
-                expr: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(Var("x".to_string())),
-                    t2: Box::new(NumConst(1))
-                })
+                var_type: Some(TypeAnn::Int),
+                expr: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(Var("x".to_string()).into()),
+                        t2: Box::new(NumConst(from_i64(1)).into())
+                    }
+                    .into()
+                )
+            }
+            .into()
+        ))
+    );
+}
+
+#[test]
+fn test_unannotated_lambda() {
+    assert_eq!(
+        parse_str(lambda, r"\y. y"),
+        Ok((
+            "",
+            Lambda {
+                var_name: "y".to_string(),
+                var_type: None,
+                expr: Box::new(Var("y".to_string()).into()),
+            }
+            .into()
+        ))
+    );
+}
+
+#[test]
+fn test_multi_lambda() {
+    let var = |name: &str| -> Box<Term> { Box::new(Var(name.to_string()).into()) };
+    let lambda = |var_name: &str, var_type, expr| -> Term {
+        Lambda {
+            var_name: var_name.to_string(),
+            var_type,
+            expr,
+        }
+        .into()
+    };
+    let body: Term = Apply {
+        var_term: var("p"),
+        function: Box::new(
+            Apply {
+                var_term: var("q"),
+                function: var("p"),
             }
+            .into(),
+        ),
+    }
+    .into();
+    let church_and = lambda("p", None, Box::new(lambda("q", None, Box::new(body))));
+    assert_eq!(
+        parse_str(term, r"\p q. p q p"),
+        Ok(("", church_and.clone()))
+    );
+    assert_eq!(
+        parse_str(term, r"(\ p. (\ q. p q p))"),
+        Ok(("", church_and))
+    );
+    assert_eq!(
+        parse_str(term, r"\x: Int y. x"),
+        Ok((
+            "",
+            lambda(
+                "x",
+                Some(TypeAnn::Int),
+                Box::new(lambda("y", None, var("x")))
+            )
+        ))
+    );
+
+    // Without parentheses, the body extends as far to the right as it can.
+    let spans = |t: &Term| match &t.kind {
+        Lambda { expr, .. } => (t.span.start, t.span.end, expr.span.start, expr.span.end),
+        _ => panic!("expected a lambda"),
+    };
+    let (_, t) = parse_str(term, r"\a b. a + b == 3").unwrap();
+    assert_eq!((0, 16, 3, 16), spans(&t));
+    assert!(parse_file(0, r"k := \x y. x; k 1 2;").is_ok());
+}
+
+#[test]
+fn test_unicode() {
+    assert_eq!(
+        parse_str(term, "λf: Int → Bool. λα. f α"),
+        parse_str(term, r"\f: Int -> Bool. \α. f α")
+    );
+    assert_eq!(parse_str(term, "λx y. x"), parse_str(term, r"\x y. x"));
+    let (_, t) = parse_str(term, "λβ. β").unwrap();
+    // Spans count bytes, and `λ` and `β` take two each.
+    assert_eq!((0, 8), (t.span.start, t.span.end));
+    assert_eq!(
+        parse_file(0, "λ. 1;").unwrap_err().expected,
+        "a variable name"
+    );
+    assert_eq!(ArrowStyle::Unicode, arrow_style(0, "λf: Int → Int. f;"));
+    assert_eq!(
+        ArrowStyle::Ascii,
+        arrow_style(0, r"\f: Int -> Int. f; -- or →")
+    );
+}
+
+#[test]
+fn test_type_ann() {
+    use ast::TypeAnn::*;
+
+    assert_eq!(parse_str(type_ann, "Bool"), Ok(("", Bool)));
+    assert_eq!(
+        parse_str(type_ann, "Int -> Bool -> Int."),
+        Ok((
+            ".",
+            Arrow(
+                Box::new(Int),
+                Box::new(Arrow(Box::new(Bool), Box::new(Int)))
+            )
+        ))
+    );
+    assert_eq!(
+        parse_str(type_ann, "(Int -> Bool) -> Int"),
+        Ok((
+            "",
+            Arrow(
+                Box::new(Arrow(Box::new(Int), Box::new(Bool))),
+                Box::new(Int)
+            )
         ))
     );
 }
@@ -195,9 +1129,9 @@ fn test_lambda() {
 #[test]
 fn test_term() {
     assert_eq!(
-        term(CompleteStr(" 1 + 2 - 3  ")),
+        parse_str(term, " 1 + 2 - 3  "),
         Ok((
-            CompleteStr(""),
+            "",
             //     -
             //    / \
             //   +   3
@@ -205,18 +1139,22 @@ fn test_term() {
             // 1   2
             MathOp {
                 opr: Minus,
-                t1: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(NumConst(1)),
-                    t2: Box::new(NumConst(2))
-                }),
-                t2: Box::new(NumConst(3))
+                t1: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(NumConst(from_i64(2)).into())
+                    }
+                    .into()
+                ),
+                t2: Box::new(NumConst(from_i64(3)).into())
             }
+            .into()
         ))
     );
 
     assert_eq!(
-        term(CompleteStr("1 + 2 * 3 - 4  ")),
+        parse_str(term, "1 + 2 * 3 - 4  "),
         //     -
         //    / \
         //   +   4
@@ -225,59 +1163,228 @@ fn test_term() {
         //    / \
         //   2   3
         Ok((
-            CompleteStr(""),
+            "",
             MathOp {
                 opr: Minus,
-                t1: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(NumConst(1)),
-                    t2: Box::new(MathOp {
-                        opr: Multiply,
-                        t1: Box::new(NumConst(2)),
-                        t2: Box::new(NumConst(3))
-                    })
-                }),
-                t2: Box::new(NumConst(4))
+                t1: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(
+                            MathOp {
+                                opr: Multiply,
+                                t1: Box::new(NumConst(from_i64(2)).into()),
+                                t2: Box::new(NumConst(from_i64(3)).into())
+                            }
+                            .into()
+                        )
+                    }
+                    .into()
+                ),
+                t2: Box::new(NumConst(from_i64(4)).into())
             }
+            .into()
         ))
     );
 
     assert_eq!(
-        term(CompleteStr("1 + 2 == 3 - 4  ")),
+        parse_str(term, "1 + 2 == 3 - 4  "),
         //      ==
         //    /    \
         //   +      -
         //  / \    / \
         // 1   2  3   4
         Ok((
-            CompleteStr(""),
+            "",
             Equals {
-                left_side: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(NumConst(1)),
-                    t2: Box::new(NumConst(2))
-                }),
-                right_side: Box::new(MathOp {
-                    opr: Minus,
-                    t1: Box::new(NumConst(3)),
-                    t2: Box::new(NumConst(4))
-                })
+                left_side: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(NumConst(from_i64(2)).into())
+                    }
+                    .into()
+                ),
+                right_side: Box::new(
+                    MathOp {
+                        opr: Minus,
+                        t1: Box::new(NumConst(from_i64(3)).into()),
+                        t2: Box::new(NumConst(from_i64(4)).into())
+                    }
+                    .into()
+                )
             }
+            .into()
         ))
     );
 
     assert_eq!(
-        term(CompleteStr("if 1 == 2 then false else true endif  ")),
+        parse_str(term, "if 1 == 2 then false else true endif  "),
         Ok((
-            CompleteStr(""),
+            "",
             IfStmt {
-                test: Box::new(Equals {
-                    left_side: Box::new(NumConst(1)),
-                    right_side: Box::new(NumConst(2))
-                }),
-                then_body: Box::new(BoolConst(false)),
-                else_body: Box::new(BoolConst(true))
+                test: Box::new(
+                    Equals {
+                        left_side: Box::new(NumConst(from_i64(1)).into()),
+                        right_side: Box::new(NumConst(from_i64(2)).into())
+                    }
+                    .into()
+                ),
+                then_body: Box::new(BoolConst(false).into()),
+                else_body: Box::new(BoolConst(true).into())
+            }
+            .into()
+        ))
+    );
+}
+
+#[test]
+fn test_negation() {
+    assert_eq!(
+        parse_str(term, "-3 * -x"),
+        Ok((
+            "",
+            MathOp {
+                opr: Multiply,
+                t1: Box::new(NumConst(from_i64(-3)).into()),
+                t2: Box::new(Negate(Box::new(Var("x".to_string()).into())).into())
+            }
+            .into()
+        ))
+    );
+    assert_eq!(
+        parse_str(term, "x -1"),
+        Ok((
+            "",
+            MathOp {
+                opr: Minus,
+                t1: Box::new(Var("x".to_string()).into()),
+                t2: Box::new(NumConst(from_i64(1)).into())
+            }
+            .into()
+        ))
+    );
+    assert_eq!(
+        parse_str(term, "- f 1"),
+        Ok((
+            "",
+            Negate(Box::new(
+                Apply {
+                    var_term: Box::new(NumConst(from_i64(1)).into()),
+                    function: Box::new(Var("f".to_string()).into())
+                }
+                .into()
+            ))
+            .into()
+        ))
+    );
+}
+
+#[test]
+fn test_power() {
+    let num = |n| -> Box<Term> { Box::new(NumConst(from_i64(n)).into()) };
+    let op = |opr, t1, t2| -> Box<Term> { Box::new(MathOp { opr, t1, t2 }.into()) };
+    assert_eq!(
+        parse_str(term, "2 ^ 3 ^ 2"),
+        Ok(("", *op(Power, num(2), op(Power, num(3), num(2)))))
+    );
+    assert_eq!(
+        parse_str(term, "-2 ^ 2"),
+        Ok(("", Negate(op(Power, num(2), num(2))).into()))
+    );
+    assert_eq!(
+        parse_str(term, "2 * 3 ^ 2 % 5"),
+        Ok((
+            "",
+            *op(
+                Modulo,
+                op(Multiply, num(2), op(Power, num(3), num(2))),
+                num(5)
+            )
+        ))
+    );
+    assert_eq!(
+        parse_str(term, "2 ^ -1"),
+        Ok(("", *op(Power, num(2), num(-1))))
+    );
+}
+
+#[test]
+fn test_compare() {
+    assert_eq!(
+        parse_str(term, "x + 1 <= 2 * y"),
+        Ok((
+            "",
+            Compare {
+                opr: LessEq,
+                left_side: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(Var("x".to_string()).into()),
+                        t2: Box::new(NumConst(from_i64(1)).into())
+                    }
+                    .into()
+                ),
+                right_side: Box::new(
+                    MathOp {
+                        opr: Multiply,
+                        t1: Box::new(NumConst(from_i64(2)).into()),
+                        t2: Box::new(Var("y".to_string()).into())
+                    }
+                    .into()
+                )
+            }
+            .into()
+        ))
+    );
+    assert_eq!(
+        parse_str(term, "a>b"),
+        Ok((
+            "",
+            Compare {
+                opr: Greater,
+                left_side: Box::new(Var("a".to_string()).into()),
+                right_side: Box::new(Var("b".to_string()).into())
+            }
+            .into()
+        ))
+    );
+}
+
+#[test]
+fn test_logic() {
+    let var = |name: &str| -> Box<Term> { Box::new(Var(name.to_string()).into()) };
+    assert_eq!(
+        parse_str(term, "a || !b && x < y || c"),
+        Ok((
+            "",
+            Logic {
+                opr: Or,
+                left_side: Box::new(
+                    Logic {
+                        opr: Or,
+                        left_side: var("a"),
+                        right_side: Box::new(
+                            Logic {
+                                opr: And,
+                                left_side: Box::new(Not(var("b")).into()),
+                                right_side: Box::new(
+                                    Compare {
+                                        opr: Less,
+                                        left_side: var("x"),
+                                        right_side: var("y")
+                                    }
+                                    .into()
+                                )
+                            }
+                            .into()
+                        )
+                    }
+                    .into()
+                ),
+                right_side: var("c")
             }
+            .into()
         ))
     );
 }
@@ -285,20 +1392,27 @@ fn test_term() {
 #[test]
 fn test_apply() {
     assert_eq!(
-        term(CompleteStr("a b c + 5")),
+        parse_str(term, "a b c + 5"),
         Ok((
-            CompleteStr(""),
+            "",
             MathOp {
                 opr: Add,
-                t1: Box::new(Apply {
-                    var_term: Box::new(Var("c".to_string())),
-                    function: Box::new(Apply {
-                        var_term: Box::new(Var("b".to_string())),
-                        function: Box::new(Var("a".to_string()))
-                    })
-                }),
-                t2: Box::new(NumConst(5))
+                t1: Box::new(
+                    Apply {
+                        var_term: Box::new(Var("c".to_string()).into()),
+                        function: Box::new(
+                            Apply {
+                                var_term: Box::new(Var("b".to_string()).into()),
+                                function: Box::new(Var("a".to_string()).into())
+                            }
+                            .into()
+                        )
+                    }
+                    .into()
+                ),
+                t2: Box::new(NumConst(from_i64(5)).into())
             }
+            .into()
         ))
     );
 }
@@ -306,17 +1420,21 @@ fn test_apply() {
 #[test]
 fn test_assignment() {
     assert_eq!(
-        assignment(CompleteStr("_a := 1 + 1")),
+        parse_str(assignment, "_a := 1 + 1"),
         Ok((
-            CompleteStr(""),
+            "",
             Assignm {
                 var_name: "_a".to_string(),
-                expr: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(NumConst(1)),
-                    t2: Box::new(NumConst(1))
-                })
+                expr: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(NumConst(from_i64(1)).into())
+                    }
+                    .into()
+                )
             }
+            .into()
         ))
     );
 }
@@ -324,17 +1442,702 @@ fn test_assignment() {
 #[test]
 fn test_parse_file() {
     assert_eq!(
-        parse_file("a := 1 + 1; a;"),
+        parse_file(0, "a := 1 + 1; a;"),
         Ok(vec![
             Assignm {
                 var_name: "a".to_string(),
-                expr: Box::new(MathOp {
-                    opr: Add,
-                    t1: Box::new(NumConst(1)),
-                    t2: Box::new(NumConst(1)),
-                }),
+                expr: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(NumConst(from_i64(1)).into()),
+                    }
+                    .into()
+                ),
+            }
+            .into(),
+            Var("a".to_string()).into(),
+        ])
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    assert_eq!(
+        parse_file(0, "a := 1;\nif a == 1 then 2 endif;"),
+        Err(ParseError {
+            code: E_SYNTAX,
+            span: Span {
+                file: 0,
+                start: 25,
+                end: 30
             },
-            Var("a".to_string()),
+            line: 2,
+            column: 18,
+            expected: "`else`",
+            found: "`endif`".to_string(),
+            help: Some("conditionals are written `if c then t else f endif`"),
+        })
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.line, e.column, e.expected, e.found)
+    };
+    assert_eq!(
+        (20, 1, 21, "`)`", "`;`".to_string()),
+        error("a := (\\x: Int. x + 1;")
+    );
+    assert_eq!(
+        (17, 3, 1, "a term", "end of input".to_string()),
+        error("a := 1;\nb := a +\n")
+    );
+    assert_eq!((3, 1, 4, "`;`", "end of input".to_string()), error("a b"));
+}
+
+#[test]
+#[cfg(not(feature = "bigint"))]
+fn test_number_too_large() {
+    let too_large = |source| {
+        let e = parse_file(0, source).unwrap_err();
+        assert_eq!(E_NUMBER_TOO_LARGE, e.code);
+        (e.span.start, e.span.end, e.found)
+    };
+    assert_eq!(
+        (4, 24, "`99999999999999999999`".to_string()),
+        too_large("1 + 99999999999999999999;")
+    );
+    assert_eq!(
+        (0, 19, "`9223372036854775808`".to_string()),
+        too_large("9223372036854775808;")
+    );
+    assert_eq!(
+        (1, 21, "`-9223372036854775809`".to_string()),
+        too_large("(-9223372036854775809);")
+    );
+}
+
+#[test]
+fn test_comments() {
+    let one_plus = |n| -> Term {
+        MathOp {
+            opr: Add,
+            t1: Box::new(NumConst(from_i64(1)).into()),
+            t2: Box::new(NumConst(from_i64(n)).into()),
+        }
+        .into()
+    };
+    assert_eq!(
+        parse_file(
+            0,
+            "-- leading\n1 + {- inline -} 2; -- trailing\n{- between -}\n1 --2\n+ 3;\n-- last"
+        ),
+        Ok(vec![one_plus(2), one_plus(3)])
+    );
+    assert_eq!(
+        parse_file(0, "{- outer {- inner -} still a comment -} 1 + 4;"),
+        Ok(vec![one_plus(4)])
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected, e.found)
+    };
+    assert_eq!(
+        (3, "`-}`", "end of input".to_string()),
+        error("1; {- outer {- inner -}")
+    );
+    assert_eq!((15, "a term", "`;`".to_string()), error("1 + {- note -} ;"));
+}
+
+#[test]
+fn test_let() {
+    let var = |name: &str| -> Box<Term> { Box::new(Var(name.to_string()).into()) };
+    assert_eq!(
+        parse_str(term, "let x = 1 + 2 in x * x"),
+        Ok((
+            "",
+            Let {
+                name: "x".to_string(),
+                value: Box::new(
+                    MathOp {
+                        opr: Add,
+                        t1: Box::new(NumConst(from_i64(1)).into()),
+                        t2: Box::new(NumConst(from_i64(2)).into()),
+                    }
+                    .into()
+                ),
+                body: Box::new(
+                    MathOp {
+                        opr: Multiply,
+                        t1: var("x"),
+                        t2: var("x"),
+                    }
+                    .into()
+                ),
+            }
+            .into()
+        ))
+    );
+    // Names that merely start with a keyword are still names.
+    assert_eq!(
+        parse_str(term, "let letter = inner in letter"),
+        Ok((
+            "",
+            Let {
+                name: "letter".to_string(),
+                value: var("inner"),
+                body: var("letter"),
+            }
+            .into()
+        ))
+    );
+    assert_eq!(parse_file(0, "let x = 1 x;").unwrap_err().expected, "`in`");
+}
+
+#[test]
+fn test_let_rec() {
+    let var = |name: &str| -> Term { Var(name.to_string()).into() };
+    let apply = |f, x| -> Term {
+        Apply {
+            var_term: Box::new(var(x)),
+            function: Box::new(var(f)),
+        }
+        .into()
+    };
+    let lambda = |body| -> Term {
+        Lambda {
+            var_name: "n".to_string(),
+            var_type: None,
+            expr: Box::new(body),
+        }
+        .into()
+    };
+    let bindings = vec![
+        ("f".to_string(), lambda(apply("g", "n"))),
+        ("g".to_string(), lambda(apply("f", "n"))),
+    ];
+    assert_eq!(
+        parse_str(term, "let rec f = (\\n. g n) and g = (\\n. f n) in f 1"),
+        Ok((
+            "",
+            LetRec {
+                bindings: bindings.clone(),
+                body: Box::new(
+                    Apply {
+                        var_term: Box::new(NumConst(from_i64(1)).into()),
+                        function: Box::new(var("f")),
+                    }
+                    .into()
+                ),
+            }
+            .into()
+        ))
+    );
+
+    // A top-level group is assigned one function at a time.
+    let assign = |(name, value): &(String, Term)| -> Term {
+        Assignm {
+            var_name: name.clone(),
+            expr: Box::new(value.clone()),
+        }
+        .into()
+    };
+    assert_eq!(
+        parse_file(0, "rec f := (\\n. g n) and g := (\\n. f n); record;"),
+        Ok(vec![
+            assign(&bindings[0]),
+            assign(&bindings[1]),
+            var("record")
         ])
     );
 }
+
+#[test]
+fn test_parse_error_display() {
+    assert_eq!(
+        parse_file(0, "1 + ;").unwrap_err().to_string(),
+        "line 1, column 5: expected a term, found `;`"
+    );
+}
+
+#[test]
+fn test_spans() {
+    let span = |start, end| Span {
+        file: 0,
+        start,
+        end,
+    };
+    let terms = parse_file(0, "x := 10;\nif x == 1 then (\\y: Int. y) else f x endif;").unwrap();
+
+    assert_eq!(span(0, 7), terms[0].span);
+    let if_stmt = &terms[1];
+    assert_eq!(span(9, 51), if_stmt.span);
+    match &if_stmt.kind {
+        IfStmt {
+            test,
+            then_body,
+            else_body,
+        } => {
+            assert_eq!(span(12, 18), test.span);
+            // A parenthesized term spans its parentheses.
+            assert_eq!(span(24, 36), then_body.span);
+            assert_eq!(span(42, 45), else_body.span);
+        }
+        _ => panic!("expected an if statement"),
+    }
+}
+
+#[test]
+fn test_tuple() {
+    let num = |n| -> Term { NumConst(from_i64(n)).into() };
+    let pair = Tuple(vec![num(1), Var("x".to_string()).into()]);
+    assert_eq!(parse_str(term, "(1, x)"), Ok(("", pair.clone().into())));
+    // A single parenthesized term is not a tuple.
+    assert_eq!(parse_str(term, "(1)"), Ok(("", num(1))));
+    assert_eq!(
+        parse_str(term, "f (1, x).1"),
+        Ok((
+            "",
+            Apply {
+                var_term: Box::new(
+                    Project {
+                        tuple: Box::new(pair.into()),
+                        index: 1
+                    }
+                    .into()
+                ),
+                function: Box::new(Var("f".to_string()).into())
+            }
+            .into()
+        ))
+    );
+    assert_eq!(
+        parse_str(type_ann, "(Int, Bool -> Int)"),
+        Ok((
+            "",
+            TypeAnn::Tuple(vec![
+                TypeAnn::Int,
+                TypeAnn::Arrow(Box::new(TypeAnn::Bool), Box::new(TypeAnn::Int))
+            ])
+        ))
+    );
+    let (_, t) = parse_str(term, "(1, 2).0").unwrap();
+    assert_eq!((0, 8), (t.span.start, t.span.end));
+    assert_eq!(
+        parse_file(0, "(1, 2).;").unwrap_err().expected,
+        "a tuple index or a field name"
+    );
+}
+
+#[test]
+fn test_record() {
+    let num = |n| -> Term { NumConst(from_i64(n)).into() };
+    let point = Record(vec![("y".to_string(), num(2)), ("x".to_string(), num(1))]);
+    assert_eq!(
+        parse_str(term, "{ y = 2, x = 1 }.x"),
+        Ok((
+            "",
+            Field {
+                record: Box::new(point.into()),
+                field: "x".to_string()
+            }
+            .into()
+        ))
+    );
+    assert_eq!(parse_str(term, "{}"), Ok(("", Record(vec![]).into())));
+    assert_eq!(
+        parse_str(type_ann, "{ x: Int, f: Int -> Bool }"),
+        Ok((
+            "",
+            TypeAnn::Record(vec![
+                ("x".to_string(), TypeAnn::Int),
+                (
+                    "f".to_string(),
+                    TypeAnn::Arrow(Box::new(TypeAnn::Int), Box::new(TypeAnn::Bool))
+                )
+            ])
+        ))
+    );
+    // `{-` always starts a comment.
+    assert_eq!(
+        parse_str(term, "{ x = {- one -} 1 }"),
+        Ok(("", Record(vec![("x".to_string(), num(1))]).into()))
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected)
+    };
+    assert_eq!(
+        (9, "a field name that is not already used"),
+        error("{ x = 1, x = 2 };")
+    );
+    assert_eq!((9, "a field name"), error("{ x = 1, };"));
+    assert_eq!((3, "`=`"), error("{ x: 1 };"));
+    assert_eq!((8, "`}`"), error("{ x = 1 ;"));
+}
+
+#[test]
+fn test_data() {
+    let terms = parse_file(
+        0,
+        "data Shape = Circle Int | Rect Int Int | Pair (Shape, Bool);",
+    )
+    .unwrap();
+    assert_eq!(
+        vec![Term::from(Data {
+            name: "Shape".to_string(),
+            constructors: vec![
+                ("Circle".to_string(), vec![TypeAnn::Int]),
+                ("Rect".to_string(), vec![TypeAnn::Int, TypeAnn::Int]),
+                (
+                    "Pair".to_string(),
+                    vec![TypeAnn::Tuple(vec![
+                        TypeAnn::Named("Shape".to_string()),
+                        TypeAnn::Bool
+                    ])]
+                ),
+            ]
+        })],
+        terms
+    );
+    assert_eq!((0, 59), (terms[0].span.start, terms[0].span.end));
+    assert_eq!(
+        parse_file(0, "data Color =\n  | Red\n  | Green;").unwrap(),
+        vec![Term::from(Data {
+            name: "Color".to_string(),
+            constructors: vec![("Red".to_string(), vec![]), ("Green".to_string(), vec![])]
+        })]
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected)
+    };
+    assert_eq!((5, "a type name"), error("data = A;"));
+    assert_eq!((10, "a constructor name"), error("data T = |;"));
+    assert_eq!((11, "`;`"), error("data T = A -> B;"));
+    assert_eq!(
+        Some(
+            "data types are written `data Name = Con T ... | ...;`, with each constructor \
+             named before the types of its fields"
+        ),
+        parse_file(0, "data T = ;").unwrap_err().help
+    );
+}
+
+#[test]
+fn test_case() {
+    let var = |name: &str| -> Term { Var(name.to_string()).into() };
+    let arm = |constructor: &str, vars: &[&str], body| CaseArm {
+        constructor: constructor.to_string(),
+        vars: vars.iter().map(|v| v.to_string()).collect(),
+        body,
+        span: Span::default(),
+    };
+    let (rest, t) = parse_str(term, "case s of Circle r -> r | Rect w h -> w * h end + 1").unwrap();
+    assert_eq!("", rest);
+    assert_eq!(
+        t,
+        MathOp {
+            opr: Add,
+            t1: Box::new(
+                Case {
+                    scrutinee: Box::new(var("s")),
+                    arms: vec![
+                        arm("Circle", &["r"], var("r")),
+                        arm(
+                            "Rect",
+                            &["w", "h"],
+                            MathOp {
+                                opr: Multiply,
+                                t1: Box::new(var("w")),
+                                t2: Box::new(var("h"))
+                            }
+                            .into()
+                        ),
+                    ]
+                }
+                .into()
+            ),
+            t2: Box::new(NumConst(from_i64(1)).into())
+        }
+        .into()
+    );
+    match &t.kind {
+        MathOp { t1, .. } => match &t1.kind {
+            Case { arms, .. } => assert_eq!((26, 34), (arms[1].span.start, arms[1].span.end)),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+    // A leading `|` is allowed, and `||` inside an arm is not a separator.
+    assert_eq!(
+        parse_str(term, "case b of | B x -> x || y end"),
+        Ok((
+            "",
+            Case {
+                scrutinee: Box::new(var("b")),
+                arms: vec![arm(
+                    "B",
+                    &["x"],
+                    Logic {
+                        opr: Or,
+                        left_side: Box::new(var("x")),
+                        right_side: Box::new(var("y"))
+                    }
+                    .into()
+                )]
+            }
+            .into()
+        ))
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected)
+    };
+    assert_eq!(
+        (17, "a variable name that is not already used"),
+        error("case p of Rect w w -> w end;")
+    );
+    assert!(parse_file(0, "case p of Rect _ _ -> 1 end;").is_ok());
+    assert_eq!((7, "`of`"), error("case p -> 1 end;"));
+    assert_eq!((16, "`->`"), error("case p of Rect w. w end;"));
+    assert_eq!((21, "`end`"), error("case p of Rect w -> w;"));
+    assert_eq!(
+        (24, "a constructor name"),
+        error("case p of Rect w -> w | end;")
+    );
+}
+
+#[test]
+fn test_match() {
+    let arm = |pattern, body: Term| MatchArm {
+        pattern,
+        body,
+        span: Span::default(),
+    };
+    let num = |n| -> Term { NumConst(from_i64(n)).into() };
+    let (rest, t) = parse_str(
+        term,
+        "match n with | 0 -> 1 | -1 -> 2 | true -> 3 | _ -> 4 | m -> m end",
+    )
+    .unwrap();
+    assert_eq!("", rest);
+    assert_eq!(
+        t,
+        Match {
+            scrutinee: Box::new(Var("n".to_string()).into()),
+            arms: vec![
+                arm(Pattern::Num(from_i64(0)), num(1)),
+                arm(Pattern::Num(from_i64(-1)), num(2)),
+                arm(Pattern::Bool(true), num(3)),
+                arm(Pattern::Wildcard, num(4)),
+                arm(Pattern::Var("m".to_string()), Var("m".to_string()).into()),
+            ]
+        }
+        .into()
+    );
+    match &t.kind {
+        Match { arms, .. } => assert_eq!((24, 26), (arms[1].span.start, arms[1].span.end)),
+        _ => unreachable!(),
+    }
+    // The leading `|` is optional.
+    assert_eq!(
+        parse_str(term, "match b with false -> 0 end"),
+        Ok((
+            "",
+            Match {
+                scrutinee: Box::new(Var("b".to_string()).into()),
+                arms: vec![arm(Pattern::Bool(false), num(0))]
+            }
+            .into()
+        ))
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected)
+    };
+    assert_eq!((8, "`with`"), error("match n | 0 -> 1 end;"));
+    assert_eq!((15, "a pattern"), error("match n with | + -> 1 end;"));
+    assert_eq!((17, "`->`"), error("match n with | 0 => 1 end;"));
+}
+
+#[test]
+fn test_list() {
+    let num = |n| -> Term { NumConst(from_i64(n)).into() };
+    let var = |name: &str| -> Term { Var(name.to_string()).into() };
+    let cons = |head, tail| -> Term {
+        Cons {
+            head: Box::new(head),
+            tail: Box::new(tail),
+        }
+        .into()
+    };
+    assert_eq!(parse_str(term, "[]"), Ok(("", List(vec![]).into())));
+    assert_eq!(
+        parse_str(term, "[1, 2 + 3]"),
+        Ok((
+            "",
+            List(vec![
+                num(1),
+                MathOp {
+                    opr: Add,
+                    t1: Box::new(num(2)),
+                    t2: Box::new(num(3)),
+                }
+                .into(),
+            ])
+            .into()
+        ))
+    );
+    // `::` associates to the right, and binds more loosely than `+` but more
+    // tightly than `==`.
+    assert_eq!(
+        parse_str(term, "1 :: x + 1 :: xs == ys"),
+        Ok((
+            "",
+            Equals {
+                left_side: Box::new(cons(
+                    num(1),
+                    cons(
+                        MathOp {
+                            opr: Add,
+                            t1: Box::new(var("x")),
+                            t2: Box::new(num(1)),
+                        }
+                        .into(),
+                        var("xs"),
+                    ),
+                )),
+                right_side: Box::new(var("ys")),
+            }
+            .into()
+        ))
+    );
+    let (_, t) = parse_str(term, "f [x]").unwrap();
+    assert_eq!(
+        (2, 5),
+        (t.subterms()[1].span.start, t.subterms()[1].span.end)
+    );
+
+    assert_eq!(
+        parse_str(type_ann, "List (List Int) -> List Bool"),
+        Ok((
+            "",
+            TypeAnn::Arrow(
+                Box::new(TypeAnn::List(Box::new(TypeAnn::List(Box::new(
+                    TypeAnn::Int
+                ))))),
+                Box::new(TypeAnn::List(Box::new(TypeAnn::Bool))),
+            )
+        ))
+    );
+
+    // `[x, _]` is short for `x :: _ :: []`.
+    let (_, t) = parse_str(
+        term,
+        "match l with | [] -> 0 | [x, _] -> 1 | (0 :: _) :: rest -> 2 end",
+    )
+    .unwrap();
+    let pattern = |p: &str| Pattern::Var(p.to_string());
+    let cons = |head, tail| Pattern::Cons(Box::new(head), Box::new(tail));
+    match &t.kind {
+        Match { arms, .. } => {
+            let patterns: Vec<&Pattern> = arms.iter().map(|arm| &arm.pattern).collect();
+            assert_eq!(
+                vec![
+                    &Pattern::Nil,
+                    &cons(pattern("x"), cons(Pattern::Wildcard, Pattern::Nil)),
+                    &cons(
+                        cons(Pattern::Num(from_i64(0)), Pattern::Wildcard),
+                        pattern("rest")
+                    ),
+                ],
+                patterns
+            );
+            assert_eq!("(0 :: _) :: rest", arms[2].pattern.to_string());
+            assert_eq!((39, 55), (arms[2].span.start, arms[2].span.end));
+        }
+        _ => unreachable!(),
+    }
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected)
+    };
+    assert_eq!((5, "`]`"), error("[1, 2;"));
+    assert_eq!((3, "a term"), error("[1,];"));
+    assert_eq!((9, "a term"), error("x := 1 ::;"));
+    assert_eq!((18, "`]`"), error("match l with | [x y] -> x end;"));
+}
+
+#[test]
+fn test_string() {
+    let string = |s: &str| -> Term { StrConst(s.to_string()).into() };
+    assert_eq!(
+        parse_str(term, r#""tab\t\"quoted\" \\ λ\n""#),
+        Ok(("", string("tab\t\"quoted\" \\ λ\n")))
+    );
+    assert_eq!(parse_str(term, r#""""#), Ok(("", string(""))));
+    // `++` binds like `+`, and less tightly than application.
+    assert_eq!(
+        parse_str(term, r#""n" ++ show 1 ++ "!""#),
+        Ok((
+            "",
+            Concat {
+                left_side: Box::new(
+                    Concat {
+                        left_side: Box::new(string("n")),
+                        right_side: Box::new(
+                            Apply {
+                                var_term: Box::new(NumConst(from_i64(1)).into()),
+                                function: Box::new(Var("show".to_string()).into()),
+                            }
+                            .into()
+                        ),
+                    }
+                    .into()
+                ),
+                right_side: Box::new(string("!")),
+            }
+            .into()
+        ))
+    );
+    assert_eq!(
+        parse_str(type_ann, "Str -> List Str"),
+        Ok((
+            "",
+            TypeAnn::Arrow(
+                Box::new(TypeAnn::Str),
+                Box::new(TypeAnn::List(Box::new(TypeAnn::Str)))
+            )
+        ))
+    );
+
+    let error = |src| {
+        let e = parse_file(0, src).unwrap_err();
+        (e.span.start, e.expected, e.found)
+    };
+    assert_eq!(
+        (
+            5,
+            "a string that ends with `\"` on the same line",
+            r#"`"abc;`"#.to_string()
+        ),
+        error("s := \"abc;\n")
+    );
+    assert_eq!(
+        (
+            5,
+            "a string whose escapes are `\\n`, `\\t`, `\\r`, `\\\"` or `\\\\`"
+        ),
+        {
+            let (start, expected, _) = error(r#"s := "a\qb";"#);
+            (start, expected)
+        }
+    );
+}
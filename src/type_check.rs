@@ -1,276 +1,1628 @@
 use ast::*;
-use std::collections::HashMap;
+use builtin::Builtin;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+#[cfg(test)]
+use num::from_i64;
+
+/// A type variable, standing for a type that inference has not pinned down.
+pub type TypeVar = usize;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum TermType {
     Int,
     Bool,
-    Func {
-        env: HashMap<String, TermType>,
+    Str,
+    /// A function from the first type to the second.
+    Arrow(Box<TermType>, Box<TermType>),
+    /// The type of tuples whose components have these types.
+    Tuple(Vec<TermType>),
+    /// The type of records with exactly these fields.
+    Record(BTreeMap<String, TermType>),
+    /// The type of lists whose elements have this type.
+    List(Box<TermType>),
+    /// A type declared with `data`, together with the names of all of its
+    /// constructors, so that a `case` can tell which ones it leaves out.
+    Data {
         name: String,
-        func_term: Box<Term>,
+        constructors: Vec<String>,
     },
+    Var(TypeVar),
 }
 
-/// This represents a binding between names and TermTypes.
-pub struct TyEnv(pub HashMap<String, TermType>);
+impl TermType {
+    /// Adds the type variables of `self` to `vars`, in order of first
+    /// appearance.
+    fn type_vars(&self, vars: &mut Vec<TypeVar>) {
+        match self {
+            TermType::Var(v) => {
+                if !vars.contains(v) {
+                    vars.push(*v)
+                }
+            }
+            TermType::Arrow(from, to) => {
+                from.type_vars(vars);
+                to.type_vars(vars);
+            }
+            TermType::Tuple(components) => {
+                for component in components {
+                    component.type_vars(vars);
+                }
+            }
+            TermType::Record(fields) => {
+                for field in fields.values() {
+                    field.type_vars(vars);
+                }
+            }
+            TermType::List(element) => element.type_vars(vars),
+            TermType::Int | TermType::Bool | TermType::Str | TermType::Data { .. } => {}
+        }
+    }
 
-// Main Type checking function.
-// This evaluates a term to a TermType or throw an error.
-pub fn type_check(term: &Term, env: &TyEnv) -> Result<TermType, String> {
-    match term {
-        Term::Var(n) => Ok(env
-            .0
-            .get(n)
-            .ok_or("Variable name missing in environment")?
-            .clone()),
-        Term::Lambda { var_name, expr } => Ok(TermType::Func {
-            env: env.0.clone(),
-            name: var_name.clone(),
-            func_term: expr.clone(),
-        }),
-        Term::Apply { var_term, function } => match type_check(function, env)? {
-            TermType::Func {
-                env: term_env,
-                name,
-                func_term,
-            } => {
-                let var_type = type_check(var_term, env)?;
-                let mut env_prime = env.0.clone();
-                for (k, v) in term_env {
-                    env_prime.insert(k, v);
+    /// Replaces the type variables in `map` with their images.
+    fn substitute(&self, map: &HashMap<TypeVar, TermType>) -> TermType {
+        match self {
+            TermType::Var(v) => map.get(v).cloned().unwrap_or_else(|| self.clone()),
+            TermType::Arrow(from, to) => {
+                TermType::Arrow(Box::new(from.substitute(map)), Box::new(to.substitute(map)))
+            }
+            TermType::Tuple(components) => {
+                TermType::Tuple(components.iter().map(|c| c.substitute(map)).collect())
+            }
+            TermType::Record(fields) => TermType::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.substitute(map)))
+                    .collect(),
+            ),
+            TermType::List(element) => TermType::List(Box::new(element.substitute(map))),
+            TermType::Int | TermType::Bool | TermType::Str | TermType::Data { .. } => self.clone(),
+        }
+    }
+
+    /// Finds the data type called `name` within `self`.
+    fn find_data(&self, name: &str) -> Option<&TermType> {
+        match self {
+            TermType::Data { name: n, .. } if n == name => Some(self),
+            TermType::Arrow(from, to) => from.find_data(name).or_else(|| to.find_data(name)),
+            TermType::Tuple(components) => components.iter().find_map(|c| c.find_data(name)),
+            TermType::Record(fields) => fields.values().find_map(|ty| ty.find_data(name)),
+            TermType::List(element) => element.find_data(name),
+            TermType::Int
+            | TermType::Bool
+            | TermType::Str
+            | TermType::Data { .. }
+            | TermType::Var(_) => None,
+        }
+    }
+}
+
+/// Prints types the way they are written in annotations, with type
+/// variables lettered `a`, `b`, ..., arrows associating to the right and
+/// record fields in order of their names:
+/// `(Int -> Bool) -> (a, { x: a }) -> List (List a) -> a`.
+impl fmt::Display for TermType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TermType::Int => write!(f, "Int"),
+            TermType::Bool => write!(f, "Bool"),
+            TermType::Str => write!(f, "Str"),
+            TermType::Data { name, .. } => write!(f, "{}", name),
+            TermType::Var(v) => {
+                let letter = (b'a' + (v % 26) as u8) as char;
+                match v / 26 {
+                    0 => write!(f, "{}", letter),
+                    n => write!(f, "{}{}", letter, n),
                 }
-                env_prime.insert(name.clone(), var_type);
-                type_check(&func_term, &TyEnv(env_prime))
             }
-            _ => Err("terms need to be applied to function types".to_string()),
-        },
-        Term::NumConst(_) => Ok(TermType::Int),
-        Term::BoolConst(_) => Ok(TermType::Bool),
-        Term::MathOp { t1, t2, .. } => {
-            type_check_bin_math_op(&type_check(t1, env)?, &type_check(t2, env)?)
-        }
-        Term::Equals {
-            left_side: t1,
-            right_side: t2,
-        } => type_check_bin_logic_op(&type_check(t1, env)?, &type_check(t2, env)?),
-        Term::NotEquals {
-            left_side: t1,
-            right_side: t2,
-        } => type_check_bin_logic_op(&type_check(t1, env)?, &type_check(t2, env)?),
-        Term::IfStmt {
-            test: c,
-            then_body: tb,
-            else_body: eb,
-        } => type_check_if(
-            &type_check(c, env)?,
-            &type_check(tb, env)?,
-            &type_check(eb, env)?,
+            TermType::Arrow(from, to) => match **from {
+                TermType::Arrow(..) => write!(f, "({}) -> {}", from, to),
+                _ => write!(f, "{} -> {}", from, to),
+            },
+            TermType::Tuple(components) => {
+                let components: Vec<String> = components.iter().map(|c| c.to_string()).collect();
+                write!(f, "({})", components.join(", "))
+            }
+            TermType::List(element) => match **element {
+                TermType::Arrow(..) | TermType::List(_) => write!(f, "List ({})", element),
+                _ => write!(f, "List {}", element),
+            },
+            TermType::Record(fields) if fields.is_empty() => write!(f, "{{}}"),
+            TermType::Record(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+        }
+    }
+}
+
+/// The type that `ann` stands for, where `data_type` looks up the types
+/// declared with `data`. Fails with the first name it does not know.
+fn ann_type(
+    ann: &TypeAnn,
+    data_type: &dyn Fn(&str) -> Option<TermType>,
+) -> Result<TermType, String> {
+    Ok(match ann {
+        TypeAnn::Int => TermType::Int,
+        TypeAnn::Bool => TermType::Bool,
+        TypeAnn::Str => TermType::Str,
+        TypeAnn::Arrow(from, to) => TermType::Arrow(
+            Box::new(ann_type(from, data_type)?),
+            Box::new(ann_type(to, data_type)?),
+        ),
+        TypeAnn::Tuple(components) => TermType::Tuple(
+            components
+                .iter()
+                .map(|c| ann_type(c, data_type))
+                .collect::<Result<_, _>>()?,
         ),
-        Term::Assignm { expr, .. } => type_check(expr, env),
+        TypeAnn::Record(fields) => TermType::Record(
+            fields
+                .iter()
+                .map(|(name, ann)| Ok((name.clone(), ann_type(ann, data_type)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        TypeAnn::List(element) => TermType::List(Box::new(ann_type(element, data_type)?)),
+        TypeAnn::Named(name) => data_type(name).ok_or_else(|| name.clone())?,
+    })
+}
+
+fn unknown_type(name: &str, span: Span) -> TypeError {
+    TypeError::new(E_UNKNOWN_TYPE, format!("unknown type `{}`", name), span).with_help(format!(
+        "types are `Int`, `Bool`, `Str`, functions, tuples, records, lists and data types \
+         declared with `data {} = ...;`",
+        name
+    ))
+}
+
+/// The types that an overloaded builtin works on.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Class {
+    /// Lists and strings, which `length` counts.
+    Sized,
+    /// Integers and booleans, which `show` turns into strings.
+    Showable,
+}
+
+impl Class {
+    /// Whether a type of the shape of `ty`, which is not a type variable,
+    /// is in the class.
+    fn contains(self, ty: &TermType) -> bool {
+        match self {
+            Class::Sized => matches!(ty, TermType::List(_) | TermType::Str),
+            Class::Showable => matches!(ty, TermType::Int | TermType::Bool),
+        }
     }
 }
 
-fn type_check_bin_math_op(t1: &TermType, t2: &TermType) -> Result<TermType, String> {
-    match (t1, t2) {
-        (TermType::Int, TermType::Int) => Ok(TermType::Int),
-        (_, _) => {
-            Err("Mathematical binary operators require both parameters to be integers".to_string())
+/// A type that is polymorphic in `vars`: every use of a name bound to it
+/// gets its own fresh copy of those variables. The variables in `eq_vars`
+/// may only stand for types whose values can be compared with `==`, and
+/// those in `classes` only for types in their class.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Scheme {
+    pub vars: Vec<TypeVar>,
+    pub eq_vars: Vec<TypeVar>,
+    pub classes: Vec<(TypeVar, Class)>,
+    pub ty: TermType,
+}
+
+impl Scheme {
+    /// A scheme that is not polymorphic at all.
+    pub fn mono(ty: TermType) -> Scheme {
+        Scheme {
+            vars: Vec::new(),
+            eq_vars: Vec::new(),
+            classes: Vec::new(),
+            ty,
+        }
+    }
+
+    fn free_vars(&self, vars: &mut Vec<TypeVar>) {
+        let mut ty_vars = Vec::new();
+        self.ty.type_vars(&mut ty_vars);
+        for v in ty_vars {
+            if !self.vars.contains(&v) && !vars.contains(&v) {
+                vars.push(v);
+            }
         }
     }
 }
 
-fn type_check_bin_logic_op(t1: &TermType, t2: &TermType) -> Result<TermType, String> {
-    match (t1, t2) {
-        (TermType::Int, TermType::Int) => Ok(TermType::Bool),
-        (TermType::Bool, TermType::Bool) => Ok(TermType::Bool),
-        (_, _) => Err(
-            "Logical binary operators require both parameters to be integers or booleans"
-                .to_string(),
-        ),
+/// This represents a binding between names and their type schemes, and how
+/// error messages write function types. Constructors are bound like any
+/// other function.
+#[derive(Default)]
+pub struct TyEnv {
+    pub vars: HashMap<String, Scheme>,
+    /// The arrow the program is written with.
+    pub arrow: ArrowStyle,
+}
+
+impl TyEnv {
+    /// An environment that binds `vars` and writes arrows as `self` does.
+    fn with_vars(&self, vars: HashMap<String, Scheme>) -> TyEnv {
+        TyEnv {
+            vars,
+            arrow: self.arrow,
+        }
+    }
+
+    /// The data type called `name`. Its constructors are in scope, so it can
+    /// be found through their types.
+    fn data_type(&self, name: &str) -> Option<TermType> {
+        self.vars
+            .values()
+            .find_map(|scheme| scheme.ty.find_data(name))
+            .cloned()
     }
 }
 
-fn type_check_if(c: &TermType, tb: &TermType, eb: &TermType) -> Result<TermType, String> {
-    match c {
-        TermType::Bool => match_type(tb, eb),
-        _ => Err("Condition to if must be a boolean".to_string()),
+// Error codes reported for type errors.
+pub const E_UNBOUND_VARIABLE: &str = "E0101";
+pub const E_NOT_A_FUNCTION: &str = "E0102";
+pub const E_MATH_OPERANDS: &str = "E0103";
+pub const E_EQUALITY_OPERANDS: &str = "E0104";
+pub const E_IF_CONDITION: &str = "E0105";
+pub const E_MISMATCH: &str = "E0106";
+pub const E_ARGUMENT_MISMATCH: &str = "E0107";
+pub const E_INFINITE_TYPE: &str = "E0108";
+pub const E_COMPARISON_OPERANDS: &str = "E0109";
+pub const E_LOGIC_OPERANDS: &str = "E0110";
+pub const E_RECURSIVE_VALUE: &str = "E0111";
+pub const E_DUPLICATE_DEFINITION: &str = "E0112";
+pub const E_PROJECTION: &str = "E0113";
+pub const E_FIELD: &str = "E0114";
+pub const E_UNKNOWN_TYPE: &str = "E0115";
+pub const E_CONSTRUCTOR: &str = "E0116";
+pub const E_NON_EXHAUSTIVE: &str = "E0117";
+pub const E_CONCAT_OPERANDS: &str = "E0118";
+pub const E_CLASS: &str = "E0119";
+
+/// A type error, together with the span of the term that caused it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TypeError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl TypeError {
+    pub fn new<S: Into<String>>(code: &'static str, message: S, span: Span) -> TypeError {
+        TypeError {
+            code,
+            message: message.into(),
+            span,
+            help: None,
+        }
+    }
+
+    pub fn with_help<S: Into<String>>(self, help: S) -> TypeError {
+        TypeError {
+            help: Some(help.into()),
+            ..self
+        }
     }
 }
 
-fn match_type(t1: &TermType, t2: &TermType) -> Result<TermType, String> {
-    if t1 == t2 {
-        Ok(t1.clone())
-    } else {
-        Err("Types do not match".to_string()) // be more descriptive. TODO: write a pretty printer for types
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
     }
 }
 
+impl Error for TypeError {}
+
+const EQUALITY_HELP: &str =
+    "`==` and `!=` compare two integers, two booleans, two strings, or two tuples, records or \
+     lists of comparable values";
+
+/// The ways two types can fail to unify.
+enum UnifyError {
+    /// The types have different shapes, such as `Int` and `Bool`.
+    Mismatch,
+    /// Two record types have different fields: the first lacks `missing`,
+    /// and has `unknown`, which the second lacks.
+    Fields {
+        missing: Vec<String>,
+        unknown: Vec<String>,
+    },
+    /// The type variable would have to contain itself.
+    Infinite(TypeVar, TermType),
+    /// A type variable restricted to comparable types met this type.
+    Equality(TermType),
+    /// A type variable restricted to a class met this type, which is not
+    /// in it.
+    Class(Class, TermType),
+}
+
+/// Names record fields or constructors for an error message, where `what`
+/// is "field" or "constructor": "field `x`" or "fields `x`, `y` and `z`".
+fn name_list(what: &str, names: &[String]) -> String {
+    let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+    match names.split_last() {
+        Some((last, [])) => format!("{} {}", what, last),
+        Some((last, rest)) => format!("{}s {} and {}", what, rest.join(", "), last),
+        None => unreachable!(),
+    }
+}
+
+/// The state of Algorithm W while it infers the type of one definition.
+struct Infer {
+    /// What each solved type variable stands for.
+    subst: HashMap<TypeVar, TermType>,
+    /// Type variables that may only stand for comparable types.
+    eq_vars: HashSet<TypeVar>,
+    /// Type variables that may only stand for types in a class.
+    classes: Vec<(TypeVar, Class)>,
+    next_var: TypeVar,
+    arrow: ArrowStyle,
+}
+
+impl Infer {
+    /// Starts inference with fresh variables numbered after every variable
+    /// already mentioned in `env`.
+    fn new(env: &TyEnv) -> Infer {
+        let mut vars = Vec::new();
+        for scheme in env.vars.values() {
+            vars.extend(&scheme.vars);
+            scheme.ty.type_vars(&mut vars);
+        }
+        Infer {
+            subst: HashMap::new(),
+            eq_vars: HashSet::new(),
+            classes: Vec::new(),
+            next_var: vars.into_iter().max().map_or(0, |v| v + 1),
+            arrow: env.arrow,
+        }
+    }
+
+    fn fresh(&mut self) -> TermType {
+        self.next_var += 1;
+        TermType::Var(self.next_var - 1)
+    }
+
+    /// Applies the substitution found so far to `ty`, all the way down.
+    fn resolve(&self, ty: &TermType) -> TermType {
+        match ty {
+            TermType::Var(v) => match self.subst.get(v) {
+                Some(t) => self.resolve(t),
+                None => ty.clone(),
+            },
+            TermType::Arrow(from, to) => {
+                TermType::Arrow(Box::new(self.resolve(from)), Box::new(self.resolve(to)))
+            }
+            TermType::Tuple(components) => {
+                TermType::Tuple(components.iter().map(|c| self.resolve(c)).collect())
+            }
+            TermType::Record(fields) => TermType::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), self.resolve(ty)))
+                    .collect(),
+            ),
+            TermType::List(element) => TermType::List(Box::new(self.resolve(element))),
+            TermType::Int | TermType::Bool | TermType::Str | TermType::Data { .. } => ty.clone(),
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> TermType {
+        let mut fresh = HashMap::new();
+        for v in &scheme.vars {
+            let var = self.fresh();
+            if let TermType::Var(n) = var {
+                if scheme.eq_vars.contains(v) {
+                    self.eq_vars.insert(n);
+                }
+                for &(_, class) in scheme.classes.iter().filter(|(w, _)| w == v) {
+                    self.classes.push((n, class));
+                }
+            }
+            fresh.insert(*v, var);
+        }
+        scheme.ty.substitute(&fresh)
+    }
+
+    /// The type variables that are free in `env`, which must not be
+    /// quantified.
+    fn env_vars(&self, env: &TyEnv) -> Vec<TypeVar> {
+        let mut env_vars = Vec::new();
+        for scheme in env.vars.values() {
+            let scheme = Scheme {
+                ty: self.resolve(&scheme.ty),
+                ..scheme.clone()
+            };
+            scheme.free_vars(&mut env_vars);
+        }
+        env_vars
+    }
+
+    /// Quantifies the type variables of `ty` that are not free in `env`,
+    /// keeping their numbers so that inference can carry on around the
+    /// scheme.
+    fn quantify(&self, env: &TyEnv, ty: &TermType) -> Scheme {
+        let env_vars = self.env_vars(env);
+        let ty = self.resolve(ty);
+        let mut vars = Vec::new();
+        ty.type_vars(&mut vars);
+        vars.retain(|v| !env_vars.contains(v));
+        let eq_vars = vars
+            .iter()
+            .cloned()
+            .filter(|v| self.eq_vars.contains(v))
+            .collect();
+        let classes = self
+            .classes
+            .iter()
+            .cloned()
+            .filter(|(v, _)| vars.contains(v))
+            .collect();
+        Scheme {
+            vars,
+            eq_vars,
+            classes,
+            ty,
+        }
+    }
+
+    /// Quantifies the type variables of `ty` that are not free in `env`,
+    /// renumbering them from the smallest numbers `env` does not use.
+    fn generalize(&self, env: &TyEnv, ty: &TermType) -> Scheme {
+        let env_vars = self.env_vars(env);
+        let scheme = self.quantify(env, ty);
+
+        let mut renumber = HashMap::new();
+        let mut vars = Vec::new();
+        let mut eq_vars = Vec::new();
+        let mut next = 0;
+        for v in scheme.vars {
+            while env_vars.contains(&next) {
+                next += 1;
+            }
+            if scheme.eq_vars.contains(&v) {
+                eq_vars.push(next);
+            }
+            renumber.insert(v, TermType::Var(next));
+            vars.push(next);
+            next += 1;
+        }
+        let classes = scheme
+            .classes
+            .iter()
+            .map(|(v, class)| match renumber[v] {
+                TermType::Var(n) => (n, *class),
+                _ => unreachable!(),
+            })
+            .collect();
+        Scheme {
+            vars,
+            eq_vars,
+            classes,
+            ty: scheme.ty.substitute(&renumber),
+        }
+    }
+
+    fn unify(&mut self, t1: &TermType, t2: &TermType) -> Result<(), UnifyError> {
+        match (self.resolve(t1), self.resolve(t2)) {
+            (TermType::Int, TermType::Int)
+            | (TermType::Bool, TermType::Bool)
+            | (TermType::Str, TermType::Str) => Ok(()),
+            (TermType::Data { name: name1, .. }, TermType::Data { name: name2, .. })
+                if name1 == name2 =>
+            {
+                Ok(())
+            }
+            (TermType::Var(a), TermType::Var(b)) if a == b => Ok(()),
+            (TermType::Var(v), t) | (t, TermType::Var(v)) => self.bind(v, t),
+            (TermType::Arrow(from1, to1), TermType::Arrow(from2, to2)) => {
+                self.unify(&from1, &from2)?;
+                self.unify(&to1, &to2)
+            }
+            (TermType::Tuple(components1), TermType::Tuple(components2))
+                if components1.len() == components2.len() =>
+            {
+                for (c1, c2) in components1.iter().zip(&components2) {
+                    self.unify(c1, c2)?;
+                }
+                Ok(())
+            }
+            (TermType::Record(fields1), TermType::Record(fields2)) => {
+                let only_in = |a: &BTreeMap<String, TermType>, b: &BTreeMap<String, TermType>| {
+                    a.keys()
+                        .filter(|name| !b.contains_key(*name))
+                        .cloned()
+                        .collect::<Vec<_>>()
+                };
+                let (missing, unknown) = (only_in(&fields2, &fields1), only_in(&fields1, &fields2));
+                if !missing.is_empty() || !unknown.is_empty() {
+                    return Err(UnifyError::Fields { missing, unknown });
+                }
+                for (t1, t2) in fields1.values().zip(fields2.values()) {
+                    self.unify(t1, t2)?;
+                }
+                Ok(())
+            }
+            (TermType::List(element1), TermType::List(element2)) => {
+                self.unify(&element1, &element2)
+            }
+            (_, _) => Err(UnifyError::Mismatch),
+        }
+    }
+
+    fn bind(&mut self, v: TypeVar, ty: TermType) -> Result<(), UnifyError> {
+        let mut vars = Vec::new();
+        ty.type_vars(&mut vars);
+        if vars.contains(&v) {
+            return Err(UnifyError::Infinite(v, ty));
+        }
+        if self.eq_vars.contains(&v) {
+            self.require_equality(&ty).map_err(UnifyError::Equality)?;
+        }
+        let classes: Vec<Class> = self
+            .classes
+            .iter()
+            .filter(|(w, _)| *w == v)
+            .map(|(_, class)| *class)
+            .collect();
+        for class in classes {
+            self.require_class(&ty, class)
+                .map_err(|ty| UnifyError::Class(class, ty))?;
+        }
+        self.subst.insert(v, ty);
+        Ok(())
+    }
+
+    /// Checks that `ty` is in `class`, restricting it to the class if it is
+    /// a type variable. Fails with the type that is not in it.
+    fn require_class(&mut self, ty: &TermType, class: Class) -> Result<(), TermType> {
+        match self.resolve(ty) {
+            TermType::Var(v) => {
+                if !self.classes.contains(&(v, class)) {
+                    self.classes.push((v, class));
+                }
+                Ok(())
+            }
+            ty if class.contains(&ty) => Ok(()),
+            ty => Err(ty),
+        }
+    }
+
+    /// Checks that values of type `ty` can be compared with `==`, restricting
+    /// any type variables in it to comparable types. Fails with the type
+    /// that cannot be compared.
+    fn require_equality(&mut self, ty: &TermType) -> Result<(), TermType> {
+        match self.resolve(ty) {
+            TermType::Int | TermType::Bool | TermType::Str => Ok(()),
+            TermType::Var(v) => {
+                self.eq_vars.insert(v);
+                Ok(())
+            }
+            TermType::Tuple(components) => {
+                for component in &components {
+                    self.require_equality(component)?;
+                }
+                Ok(())
+            }
+            TermType::Record(fields) => {
+                for field in fields.values() {
+                    self.require_equality(field)?;
+                }
+                Ok(())
+            }
+            TermType::List(element) => self.require_equality(&element),
+            ty @ TermType::Arrow(..) | ty @ TermType::Data { .. } => Err(ty),
+        }
+    }
+
+    /// Unifies `t1` and `t2`. If their shapes differ, `mismatch` builds the
+    /// error from how the two types read.
+    fn unify_or<F>(
+        &mut self,
+        t1: &TermType,
+        t2: &TermType,
+        span: Span,
+        mismatch: F,
+    ) -> Result<(), TypeError>
+    where
+        F: FnOnce(&str, &str) -> TypeError,
+    {
+        self.unify(t1, t2).map_err(|e| match e {
+            UnifyError::Mismatch => {
+                let names = self.describe(&[t1, t2]);
+                mismatch(&names[0], &names[1])
+            }
+            UnifyError::Fields { missing, unknown } => {
+                let names = self.describe(&[t1, t2]);
+                let mut help = Vec::new();
+                if !missing.is_empty() {
+                    help.push(format!("missing {}", name_list("field", &missing)));
+                }
+                if !unknown.is_empty() {
+                    help.push(format!("unknown {}", name_list("field", &unknown)));
+                }
+                mismatch(&names[0], &names[1]).with_help(help.join("; "))
+            }
+            UnifyError::Infinite(var, ty) => {
+                let names = self.describe(&[&TermType::Var(var), &ty]);
+                TypeError::new(
+                    E_INFINITE_TYPE,
+                    format!("cannot construct the infinite type {} = {}", names[0], names[1]),
+                    span,
+                )
+                .with_help(
+                    "this would need a type that contains itself, such as a function applied to itself",
+                )
+            }
+            UnifyError::Equality(ty) => self.equality_error(&ty, span),
+            UnifyError::Class(class, ty) => {
+                let ty = &self.describe(&[&ty])[0];
+                let (message, help) = match class {
+                    Class::Sized => (
+                        format!("values of type {} have no length", ty),
+                        "`length` counts the elements of a list or the characters of a string",
+                    ),
+                    Class::Showable => (
+                        format!("values of type {} cannot be shown", ty),
+                        "`show` turns an integer or a boolean into a string",
+                    ),
+                };
+                TypeError::new(E_CLASS, message, span).with_help(help)
+            }
+        })
+    }
+
+    fn equality_error(&self, ty: &TermType, span: Span) -> TypeError {
+        TypeError::new(
+            E_EQUALITY_OPERANDS,
+            format!(
+                "values of type {} cannot be compared with `==` or `!=`",
+                self.describe(&[ty])[0]
+            ),
+            span,
+        )
+        .with_help(EQUALITY_HELP)
+    }
+
+    /// Names types for an error message. What inference has found so far is
+    /// filled in, and the remaining type variables are lettered `a`, `b`, ...
+    /// in order of appearance across all of `types`. Function types are
+    /// written with the program's arrow.
+    fn describe(&self, types: &[&TermType]) -> Vec<String> {
+        let resolved: Vec<TermType> = types.iter().map(|ty| self.resolve(ty)).collect();
+        let mut vars = Vec::new();
+        for ty in &resolved {
+            ty.type_vars(&mut vars);
+        }
+        let letters = vars
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (v, TermType::Var(i)))
+            .collect();
+        resolved
+            .iter()
+            .map(|ty| {
+                // Only arrows print as `->`: everything else in a type is a
+                // name or a bracket.
+                let name = ty.substitute(&letters).to_string();
+                match self.arrow {
+                    ArrowStyle::Ascii => name,
+                    ArrowStyle::Unicode => name.replace("->", "→"),
+                }
+            })
+            .collect()
+    }
+
+    /// Infers the types of a group of definitions that may all refer to each
+    /// other. Within the group each name has a single type, which is only
+    /// generalized once the whole group has been inferred.
+    fn infer_rec_group(
+        &mut self,
+        bindings: &[(String, Term)],
+        env: &TyEnv,
+    ) -> Result<Vec<TermType>, TypeError> {
+        let mut group_env = env.vars.clone();
+        let mut types = Vec::new();
+        for (name, value) in bindings {
+            if !matches!(value.kind, TermKind::Lambda { .. }) {
+                return Err(TypeError::new(
+                    E_RECURSIVE_VALUE,
+                    format!(
+                        "`{}` is defined recursively, so it must be a function",
+                        name
+                    ),
+                    value.span,
+                )
+                .with_help("only lambdas can refer to themselves"));
+            }
+            let ty = self.fresh();
+            group_env.insert(name.clone(), Scheme::mono(ty.clone()));
+            types.push(ty);
+        }
+        let group_env = env.with_vars(group_env);
+        for ((name, value), ty) in bindings.iter().zip(&types) {
+            let value_type = self.infer(value, &group_env)?;
+            self.unify_or(&value_type, ty, value.span, |defined, used| {
+                TypeError::new(
+                    E_MISMATCH,
+                    format!(
+                        "`{}` is defined with type {} but used as {}",
+                        name, defined, used
+                    ),
+                    value.span,
+                )
+                .with_help("within its group, a function can only be used with one type")
+            })?;
+        }
+        Ok(types)
+    }
+
+    fn infer(&mut self, term: &Term, env: &TyEnv) -> Result<TermType, TypeError> {
+        match &term.kind {
+            TermKind::Var(n) => {
+                if let (None, Some(builtin)) = (env.vars.get(n), Builtin::named(n)) {
+                    return Ok(self.instantiate(&builtin.scheme()));
+                }
+                let scheme = env.vars.get(n).ok_or_else(|| {
+                    TypeError::new(
+                        E_UNBOUND_VARIABLE,
+                        "Variable name missing in environment",
+                        term.span,
+                    )
+                    .with_help(format!("define it with `{} := ...;`", n))
+                })?;
+                Ok(self.instantiate(scheme))
+            }
+            TermKind::Lambda {
+                var_name,
+                var_type,
+                expr,
+            } => {
+                // The body is inferred once, here; applications only need to
+                // unify with the resulting arrow type.
+                let param_type = match var_type {
+                    Some(ann) => ann_type(ann, &|name| env.data_type(name))
+                        .map_err(|name| unknown_type(&name, term.span))?,
+                    None => self.fresh(),
+                };
+                let mut body_env = env.vars.clone();
+                body_env.insert(var_name.clone(), Scheme::mono(param_type.clone()));
+                let body_type = self.infer(expr, &env.with_vars(body_env))?;
+                Ok(TermType::Arrow(Box::new(param_type), Box::new(body_type)))
+            }
+            // The value is generalized before the body sees it, so
+            // `let id = (\x. x) in id id` is well-typed.
+            TermKind::Let { name, value, body } => {
+                let value_type = self.infer(value, env)?;
+                let mut body_env = env.vars.clone();
+                body_env.insert(name.clone(), self.quantify(env, &value_type));
+                self.infer(body, &env.with_vars(body_env))
+            }
+            TermKind::LetRec { bindings, body } => {
+                let types = self.infer_rec_group(bindings, env)?;
+                let mut body_env = env.vars.clone();
+                for ((name, _), ty) in bindings.iter().zip(&types) {
+                    body_env.insert(name.clone(), self.quantify(env, ty));
+                }
+                self.infer(body, &env.with_vars(body_env))
+            }
+            TermKind::Apply { var_term, function } => {
+                let function_type = self.infer(function, env)?;
+                let arg_type = self.infer(var_term, env)?;
+                match self.resolve(&function_type) {
+                    TermType::Arrow(param_type, result_type) => {
+                        self.unify_or(&arg_type, &param_type, var_term.span, |arg, param| {
+                            TypeError::new(
+                                E_ARGUMENT_MISMATCH,
+                                format!(
+                                    "argument has type {} but the function expects {}",
+                                    arg, param
+                                ),
+                                var_term.span,
+                            )
+                            .with_help(format!("pass the function a value of type {}", param))
+                        })?;
+                        Ok(*result_type)
+                    }
+                    TermType::Var(_) => {
+                        let result_type = self.fresh();
+                        let expected =
+                            TermType::Arrow(Box::new(arg_type), Box::new(result_type.clone()));
+                        self.unify_or(&function_type, &expected, term.span, |found, _| {
+                            TypeError::new(
+                                E_NOT_A_FUNCTION,
+                                format!(
+                                    "the applied term has type {}, but only functions can be applied",
+                                    found
+                                ),
+                                function.span,
+                            )
+                            .with_help(format!("apply a function, not a value of type {}", found))
+                        })?;
+                        Ok(result_type)
+                    }
+                    found => {
+                        let found = &self.describe(&[&found])[0];
+                        Err(TypeError::new(
+                            E_NOT_A_FUNCTION,
+                            format!(
+                                "the applied term has type {}, but only functions can be applied",
+                                found
+                            ),
+                            function.span,
+                        )
+                        .with_help(format!("apply a function, not a value of type {}", found)))
+                    }
+                }
+            }
+            TermKind::NumConst(_) => Ok(TermType::Int),
+            TermKind::BoolConst(_) => Ok(TermType::Bool),
+            TermKind::StrConst(_) => Ok(TermType::Str),
+            TermKind::Tuple(components) => {
+                let mut types = Vec::new();
+                for component in components {
+                    types.push(self.infer(component, env)?);
+                }
+                Ok(TermType::Tuple(types))
+            }
+            TermKind::List(elements) => {
+                let element_type = self.fresh();
+                for (i, element) in elements.iter().enumerate() {
+                    let ty = self.infer(element, env)?;
+                    self.unify_or(&ty, &element_type, element.span, |found, first| {
+                        TypeError::new(
+                            E_MISMATCH,
+                            format!(
+                                "element {} of the list has type {} but the first has type {}",
+                                i, found, first
+                            ),
+                            element.span,
+                        )
+                        .with_help("every element of a list must have the same type")
+                    })?;
+                }
+                Ok(TermType::List(Box::new(element_type)))
+            }
+            TermKind::Cons { head, tail } => {
+                let head_type = self.infer(head, env)?;
+                let tail_type = self.infer(tail, env)?;
+                let list_type = TermType::List(Box::new(head_type));
+                self.unify_or(&tail_type, &list_type, tail.span, |found, expected| {
+                    TypeError::new(
+                        E_MISMATCH,
+                        format!(
+                            "the tail of `::` has type {} but the head needs {}",
+                            found, expected
+                        ),
+                        tail.span,
+                    )
+                    .with_help("`x :: xs` puts `x` in front of `xs`, a list of values like `x`")
+                })?;
+                Ok(list_type)
+            }
+            // The tuple's type must already be known, since a type variable
+            // could stand for tuples of any length. The same goes for records
+            // below.
+            TermKind::Project { tuple, index } => {
+                let tuple_type = self.infer(tuple, env)?;
+                match self.resolve(&tuple_type) {
+                    TermType::Tuple(components) => {
+                        components.get(*index).cloned().ok_or_else(|| {
+                            TypeError::new(
+                                E_PROJECTION,
+                                format!(
+                                    "`.{}` is out of range for a tuple of type {}",
+                                    index,
+                                    self.describe(&[&tuple_type])[0]
+                                ),
+                                term.span,
+                            )
+                            .with_help(format!(
+                                "the components of this tuple are numbered from 0 to {}",
+                                components.len() - 1
+                            ))
+                        })
+                    }
+                    TermType::Var(_) => Err(TypeError::new(
+                        E_PROJECTION,
+                        format!("the type of the tuple is not known at `.{}`", index),
+                        tuple.span,
+                    )
+                    .with_help(
+                        "annotate the parameter, as in `\\p: (Int, Bool). p.0`, or use `fst` \
+                         and `snd` on pairs",
+                    )),
+                    found => Err(TypeError::new(
+                        E_PROJECTION,
+                        format!(
+                            "`.{}` needs a tuple, but the term has type {}",
+                            index,
+                            self.describe(&[&found])[0]
+                        ),
+                        tuple.span,
+                    )
+                    .with_help("`.0`, `.1`, ... take the components of a tuple")),
+                }
+            }
+            TermKind::Record(fields) => {
+                let mut types = BTreeMap::new();
+                for (name, value) in fields {
+                    types.insert(name.clone(), self.infer(value, env)?);
+                }
+                Ok(TermType::Record(types))
+            }
+            TermKind::Field { record, field } => {
+                let record_type = self.infer(record, env)?;
+                match self.resolve(&record_type) {
+                    TermType::Record(fields) => fields.get(field).cloned().ok_or_else(|| {
+                        let names: Vec<String> = fields.keys().cloned().collect();
+                        let error = TypeError::new(
+                            E_FIELD,
+                            format!(
+                                "a record of type {} has no field `{}`",
+                                self.describe(&[&record_type])[0],
+                                field
+                            ),
+                            term.span,
+                        );
+                        match names.len() {
+                            0 => error.with_help("this record has no fields"),
+                            _ => error.with_help(format!("it has {}", name_list("field", &names))),
+                        }
+                    }),
+                    TermType::Var(_) => Err(TypeError::new(
+                        E_FIELD,
+                        format!("the type of the record is not known at `.{}`", field),
+                        record.span,
+                    )
+                    .with_help(format!(
+                        "annotate the parameter, as in `\\r: {{ {}: Int }}. r.{}`",
+                        field, field
+                    ))),
+                    found => Err(TypeError::new(
+                        E_FIELD,
+                        format!(
+                            "`.{}` needs a record, but the term has type {}",
+                            field,
+                            self.describe(&[&found])[0]
+                        ),
+                        record.span,
+                    )
+                    .with_help(format!(
+                        "`.{}` takes the field `{}` of a record",
+                        field, field
+                    ))),
+                }
+            }
+            TermKind::Negate(operand) => {
+                let operand_type = self.infer(operand, env)?;
+                self.unify_or(&operand_type, &TermType::Int, operand.span, |found, _| {
+                    TypeError::new(
+                        E_MATH_OPERANDS,
+                        format!("operand of unary `-` has type {}, expected Int", found),
+                        operand.span,
+                    )
+                    .with_help("only integers can be negated")
+                })?;
+                Ok(TermType::Int)
+            }
+            TermKind::Not(operand) => {
+                let operand_type = self.infer(operand, env)?;
+                self.unify_or(&operand_type, &TermType::Bool, operand.span, |found, _| {
+                    TypeError::new(
+                        E_LOGIC_OPERANDS,
+                        format!("operand of `!` has type {}, expected Bool", found),
+                        operand.span,
+                    )
+                    .with_help("`!` turns `true` into `false` and `false` into `true`")
+                })?;
+                Ok(TermType::Bool)
+            }
+            TermKind::MathOp { opr, t1, t2 } => {
+                for (side, operand) in &[("left", t1), ("right", t2)] {
+                    let operand_type = self.infer(operand, env)?;
+                    self.unify_or(&operand_type, &TermType::Int, operand.span, |found, _| {
+                        TypeError::new(
+                            E_MATH_OPERANDS,
+                            format!(
+                                "{} operand of `{}` has type {}, expected Int",
+                                side, opr, found
+                            ),
+                            operand.span,
+                        )
+                        .with_help("arithmetic only works on integers")
+                    })?;
+                }
+                Ok(TermType::Int)
+            }
+            TermKind::Concat {
+                left_side,
+                right_side,
+            } => {
+                for (side, operand) in &[("left", left_side), ("right", right_side)] {
+                    let operand_type = self.infer(operand, env)?;
+                    self.unify_or(&operand_type, &TermType::Str, operand.span, |found, _| {
+                        TypeError::new(
+                            E_CONCAT_OPERANDS,
+                            format!("{} operand of `++` has type {}, expected Str", side, found),
+                            operand.span,
+                        )
+                        .with_help("`show` turns an integer or a boolean into a string")
+                    })?;
+                }
+                Ok(TermType::Str)
+            }
+            TermKind::Equals {
+                left_side,
+                right_side,
+            } => self.infer_equality("==", left_side, right_side, term.span, env),
+            TermKind::NotEquals {
+                left_side,
+                right_side,
+            } => self.infer_equality("!=", left_side, right_side, term.span, env),
+            TermKind::Compare {
+                opr,
+                left_side,
+                right_side,
+            } => {
+                for (side, operand) in &[("left", left_side), ("right", right_side)] {
+                    let operand_type = self.infer(operand, env)?;
+                    self.unify_or(&operand_type, &TermType::Int, operand.span, |found, _| {
+                        TypeError::new(
+                            E_COMPARISON_OPERANDS,
+                            format!(
+                                "{} side of `{}` has type {}, expected Int",
+                                side, opr, found
+                            ),
+                            operand.span,
+                        )
+                        .with_help("only integers can be ordered")
+                    })?;
+                }
+                Ok(TermType::Bool)
+            }
+            TermKind::Logic {
+                opr,
+                left_side,
+                right_side,
+            } => {
+                for (side, operand) in &[("left", left_side), ("right", right_side)] {
+                    let operand_type = self.infer(operand, env)?;
+                    self.unify_or(&operand_type, &TermType::Bool, operand.span, |found, _| {
+                        TypeError::new(
+                            E_LOGIC_OPERANDS,
+                            format!(
+                                "{} side of `{}` has type {}, expected Bool",
+                                side, opr, found
+                            ),
+                            operand.span,
+                        )
+                        .with_help("`&&` and `||` combine booleans")
+                    })?;
+                }
+                Ok(TermType::Bool)
+            }
+            TermKind::IfStmt {
+                test: c,
+                then_body: tb,
+                else_body: eb,
+            } => {
+                let c_type = self.infer(c, env)?;
+                self.unify_or(&c_type, &TermType::Bool, c.span, |found, _| {
+                    TypeError::new(
+                        E_IF_CONDITION,
+                        format!("condition has type {}, expected Bool", found),
+                        c.span,
+                    )
+                    .with_help("compare values with `==`, `<` and the like to get a Bool")
+                })?;
+                let tb = self.infer(tb, env)?;
+                let eb = self.infer(eb, env)?;
+                self.unify_or(&tb, &eb, term.span, |then_type, else_type| {
+                    TypeError::new(
+                        E_MISMATCH,
+                        format!(
+                            "then-branch has type {} but else-branch has type {}",
+                            then_type, else_type
+                        ),
+                        term.span,
+                    )
+                    .with_help("both branches of an `if` must have the same type")
+                })?;
+                Ok(tb)
+            }
+            TermKind::Case { scrutinee, arms } => {
+                let scrutinee_type = self.infer(scrutinee, env)?;
+                let mut matched: Vec<&str> = Vec::new();
+                let mut result = None;
+                for arm in arms {
+                    let (fields, data_type) = self.constructor(&arm.constructor, env, arm.span)?;
+                    self.unify_or(&scrutinee_type, &data_type, arm.span, |found, expected| {
+                        TypeError::new(
+                            E_MISMATCH,
+                            format!(
+                                "the scrutinee has type {} but `{}` builds {}",
+                                found, arm.constructor, expected
+                            ),
+                            arm.span,
+                        )
+                        .with_help("every arm of a `case` must use a constructor of the same type")
+                    })?;
+                    if fields.len() != arm.vars.len() {
+                        return Err(TypeError::new(
+                            E_CONSTRUCTOR,
+                            format!(
+                                "`{}` has {} fields, but the pattern names {}",
+                                arm.constructor,
+                                fields.len(),
+                                arm.vars.len()
+                            ),
+                            arm.span,
+                        )
+                        .with_help("write a variable, or `_`, for each field"));
+                    }
+                    if matched.contains(&arm.constructor.as_str()) {
+                        return Err(TypeError::new(
+                            E_CONSTRUCTOR,
+                            format!("`{}` already has an arm", arm.constructor),
+                            arm.span,
+                        )
+                        .with_help("each constructor can only have one arm"));
+                    }
+                    matched.push(&arm.constructor);
+
+                    let mut body_env = env.vars.clone();
+                    for (var, ty) in arm.vars.iter().zip(fields) {
+                        body_env.insert(var.clone(), Scheme::mono(ty));
+                    }
+                    let body_type = self.infer(&arm.body, &env.with_vars(body_env))?;
+                    match &result {
+                        None => result = Some(body_type),
+                        Some(first) => {
+                            self.unify_or(&body_type, first, arm.body.span, |arm_type, first| {
+                                TypeError::new(
+                                    E_MISMATCH,
+                                    format!(
+                                    "the arm for `{}` has type {} but the first arm has type {}",
+                                    arm.constructor, arm_type, first
+                                ),
+                                    arm.body.span,
+                                )
+                                .with_help("every arm of a `case` must have the same type")
+                            })?
+                        }
+                    }
+                }
+                if let TermType::Data { name, constructors } = self.resolve(&scrutinee_type) {
+                    let missing: Vec<String> = constructors
+                        .into_iter()
+                        .filter(|c| !matched.contains(&c.as_str()))
+                        .collect();
+                    if !missing.is_empty() {
+                        return Err(TypeError::new(
+                            E_NON_EXHAUSTIVE,
+                            format!(
+                                "`case` does not handle {}",
+                                name_list("constructor", &missing)
+                            ),
+                            term.span,
+                        )
+                        .with_help(format!("add an arm for each constructor of {}", name)));
+                    }
+                }
+                Ok(result.unwrap())
+            }
+            // Which values the arms leave out is checked by a separate pass
+            // over the whole program, once every term is well-typed.
+            TermKind::Match { scrutinee, arms } => {
+                let scrutinee_type = self.infer(scrutinee, env)?;
+                let mut result = None;
+                for arm in arms {
+                    let mut vars = Vec::new();
+                    arm.pattern.vars(&mut vars);
+                    for (i, var) in vars.iter().enumerate() {
+                        if vars[..i].contains(var) {
+                            return Err(TypeError::new(
+                                E_DUPLICATE_DEFINITION,
+                                format!("`{}` is bound twice in this pattern", var),
+                                arm.span,
+                            )
+                            .with_help("use a different name for each variable, or `_`"));
+                        }
+                    }
+                    let mut body_env = env.vars.clone();
+                    self.bind_pattern(&arm.pattern, &scrutinee_type, arm.span, &mut body_env)?;
+                    let body_type = self.infer(&arm.body, &env.with_vars(body_env))?;
+                    match &result {
+                        None => result = Some(body_type),
+                        Some(first) => {
+                            self.unify_or(&body_type, first, arm.body.span, |arm_type, first| {
+                                TypeError::new(
+                                    E_MISMATCH,
+                                    format!(
+                                        "the arm for `{}` has type {} but the first arm has type {}",
+                                        arm.pattern, arm_type, first
+                                    ),
+                                    arm.body.span,
+                                )
+                                .with_help("every arm of a `match` must have the same type")
+                            })?
+                        }
+                    }
+                }
+                Ok(result.unwrap())
+            }
+            TermKind::Assignm { expr, .. } => self.infer(expr, env),
+            TermKind::Data { .. } => {
+                unreachable!("data declarations are checked by type_check_data")
+            }
+        }
+    }
+
+    /// Checks that `pattern` can match values of type `ty`, and adds the
+    /// names it binds to `vars`.
+    fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        ty: &TermType,
+        span: Span,
+        vars: &mut HashMap<String, Scheme>,
+    ) -> Result<(), TypeError> {
+        let pattern_type = match pattern {
+            Pattern::Num(_) => TermType::Int,
+            Pattern::Bool(_) => TermType::Bool,
+            Pattern::Wildcard => return Ok(()),
+            Pattern::Var(name) => {
+                vars.insert(name.clone(), Scheme::mono(ty.clone()));
+                return Ok(());
+            }
+            Pattern::Nil | Pattern::Cons(..) => TermType::List(Box::new(self.fresh())),
+        };
+        self.unify_or(ty, &pattern_type, span, |found, expected| {
+            TypeError::new(
+                E_MISMATCH,
+                format!(
+                    "the pattern `{}` matches {} but the value it is matched against has type {}",
+                    pattern, expected, found
+                ),
+                span,
+            )
+            .with_help("every pattern of a `match` must match values of the same type")
+        })?;
+        if let (Pattern::Cons(head, tail), TermType::List(element)) = (pattern, pattern_type) {
+            self.bind_pattern(head, &element, span, vars)?;
+            self.bind_pattern(tail, &TermType::List(element), span, vars)?;
+        }
+        Ok(())
+    }
+
+    /// The field types and the data type of the constructor `name`.
+    fn constructor(
+        &mut self,
+        name: &str,
+        env: &TyEnv,
+        span: Span,
+    ) -> Result<(Vec<TermType>, TermType), TypeError> {
+        let not_constructor = || {
+            TypeError::new(
+                E_CONSTRUCTOR,
+                format!("`{}` is not a constructor", name),
+                span,
+            )
+            .with_help(
+                "constructors are declared with `data`, as in \
+                     `data Shape = Circle Int | Rect Int Int;`",
+            )
+        };
+        let mut ty = match env.vars.get(name) {
+            Some(scheme) => self.instantiate(scheme),
+            None => return Err(not_constructor()),
+        };
+        let mut fields = Vec::new();
+        loop {
+            match ty {
+                TermType::Arrow(from, to) => {
+                    fields.push(*from);
+                    ty = *to;
+                }
+                TermType::Data {
+                    ref constructors, ..
+                } if constructors.iter().any(|c| c == name) => return Ok((fields, ty)),
+                _ => return Err(not_constructor()),
+            }
+        }
+    }
+
+    fn infer_equality(
+        &mut self,
+        op: &str,
+        left_side: &Term,
+        right_side: &Term,
+        span: Span,
+        env: &TyEnv,
+    ) -> Result<TermType, TypeError> {
+        let left = self.infer(left_side, env)?;
+        let right = self.infer(right_side, env)?;
+        self.unify_or(&left, &right, span, |left, right| {
+            TypeError::new(
+                E_EQUALITY_OPERANDS,
+                format!(
+                    "left side of `{}` has type {} but right side has type {}",
+                    op, left, right
+                ),
+                span,
+            )
+            .with_help(EQUALITY_HELP)
+        })?;
+        self.require_equality(&left)
+            .map_err(|ty| self.equality_error(&ty, span))?;
+        Ok(TermType::Bool)
+    }
+}
+
+// Main Type checking function.
+// This infers the most general TermType of a term or throws an error.
+pub fn type_check(term: &Term, env: &TyEnv) -> Result<TermType, TypeError> {
+    type_check_definition(term, env).map(|scheme| scheme.ty)
+}
+
+/// Infers the type of a top-level definition and generalizes it, so that the
+/// name it is assigned to can be used at different types.
+pub fn type_check_definition(term: &Term, env: &TyEnv) -> Result<Scheme, TypeError> {
+    let mut infer = Infer::new(env);
+    let ty = infer.infer(term, env)?;
+    Ok(infer.generalize(env, &ty))
+}
+
+/// Infers the types of a group of top-level definitions that refer to each
+/// other, and generalizes each of them.
+pub fn type_check_rec_group(
+    bindings: &[(String, Term)],
+    env: &TyEnv,
+) -> Result<Vec<Scheme>, TypeError> {
+    let mut infer = Infer::new(env);
+    let types = infer.infer_rec_group(bindings, env)?;
+    Ok(types.iter().map(|ty| infer.generalize(env, ty)).collect())
+}
+
+/// Checks a group of data declarations, which may refer to each other, and
+/// returns the scheme of each of their constructors: a function from its
+/// fields to the data type, or the data type itself if it has no fields.
+pub fn type_check_data(decls: &[&Term], env: &TyEnv) -> Result<Vec<(String, Scheme)>, TypeError> {
+    let mut declared = HashMap::new();
+    for decl in decls {
+        if let TermKind::Data { name, constructors } = &decl.kind {
+            if ["Int", "Bool", "Str", "List"].contains(&name.as_str()) {
+                return Err(TypeError::new(
+                    E_DUPLICATE_DEFINITION,
+                    format!("`{}` is already a type", name),
+                    decl.span,
+                )
+                .with_help("give the data type another name"));
+            }
+            let constructors = constructors.iter().map(|(c, _)| c.clone()).collect();
+            let data_type = TermType::Data {
+                name: name.clone(),
+                constructors,
+            };
+            declared.insert(name.clone(), data_type);
+        }
+    }
+    let data_type = |name: &str| declared.get(name).cloned().or_else(|| env.data_type(name));
+
+    let mut schemes = Vec::new();
+    for decl in decls {
+        if let TermKind::Data { name, constructors } = &decl.kind {
+            for (constructor, fields) in constructors {
+                let fields = fields
+                    .iter()
+                    .map(|field| ann_type(field, &data_type))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|unknown| unknown_type(&unknown, decl.span))?;
+                let ty = fields
+                    .into_iter()
+                    .rev()
+                    .fold(declared[name].clone(), |ty, field| {
+                        TermType::Arrow(Box::new(field), Box::new(ty))
+                    });
+                schemes.push((constructor.clone(), Scheme::mono(ty)));
+            }
+        }
+    }
+    Ok(schemes)
+}
+
 /* Tests */
 
 #[test]
 fn test_num_const() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::NumConst(4);
+    let te = TyEnv::default();
+    let ast: Term = TermKind::NumConst(from_i64(4)).into();
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
 
 #[test]
 fn test_bool_const() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::BoolConst(false);
+    let te = TyEnv::default();
+    let ast: Term = TermKind::BoolConst(false).into();
     assert_eq!(Ok(TermType::Bool), type_check(&ast, &te));
 }
 
 #[test]
 fn test_bool_bin() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::Equals {
-        left_side: Box::new(Term::BoolConst(false)),
-        right_side: Box::new(Term::BoolConst(false)),
-    };
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Equals {
+        left_side: Box::new(TermKind::BoolConst(false).into()),
+        right_side: Box::new(TermKind::BoolConst(false).into()),
+    }
+    .into();
     assert_eq!(Ok(TermType::Bool), type_check(&ast, &te));
 }
 
 #[test]
 fn test_bool_bin_int() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::Equals {
-        left_side: Box::new(Term::NumConst(5)),
-        right_side: Box::new(Term::NumConst(6)),
-    };
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Equals {
+        left_side: Box::new(TermKind::NumConst(from_i64(5)).into()),
+        right_side: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
     assert_eq!(Ok(TermType::Bool), type_check(&ast, &te));
 }
 
 #[test]
 fn test_int_bin_int() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::MathOp {
+    let te = TyEnv::default();
+    let ast: Term = TermKind::MathOp {
         opr: BinMathOp::Add,
-        t1: Box::new(Term::NumConst(5)),
-        t2: Box::new(Term::NumConst(6)),
-    };
+        t1: Box::new(TermKind::NumConst(from_i64(5)).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
 
 #[test]
 fn test_int_bin_int_nested() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::MathOp {
+    let te = TyEnv::default();
+    let ast: Term = TermKind::MathOp {
         opr: BinMathOp::Minus,
-        t1: Box::new(Term::NumConst(5)),
-        t2: Box::new(Term::MathOp {
-            opr: BinMathOp::Multiply,
-            t1: Box::new(Term::MathOp {
-                opr: BinMathOp::Divide,
-                t1: Box::new(Term::NumConst(24)),
-                t2: Box::new(Term::NumConst(8)),
-            }),
-            t2: Box::new(Term::NumConst(8)),
-        }),
-    };
+        t1: Box::new(TermKind::NumConst(from_i64(5)).into()),
+        t2: Box::new(
+            TermKind::MathOp {
+                opr: BinMathOp::Multiply,
+                t1: Box::new(
+                    TermKind::MathOp {
+                        opr: BinMathOp::Divide,
+                        t1: Box::new(TermKind::NumConst(from_i64(24)).into()),
+                        t2: Box::new(TermKind::NumConst(from_i64(8)).into()),
+                    }
+                    .into(),
+                ),
+                t2: Box::new(TermKind::NumConst(from_i64(8)).into()),
+            }
+            .into(),
+        ),
+    }
+    .into();
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
 
 #[test]
 fn test_bool_bin_nested() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::NotEquals {
-        left_side: Box::new(Term::BoolConst(false)),
-        right_side: Box::new(Term::Equals {
-            left_side: Box::new(Term::NumConst(4)),
-            right_side: Box::new(Term::NumConst(1000)),
-        }),
-    };
+    let te = TyEnv::default();
+    let ast: Term = TermKind::NotEquals {
+        left_side: Box::new(TermKind::BoolConst(false).into()),
+        right_side: Box::new(
+            TermKind::Equals {
+                left_side: Box::new(TermKind::NumConst(from_i64(4)).into()),
+                right_side: Box::new(TermKind::NumConst(from_i64(1000)).into()),
+            }
+            .into(),
+        ),
+    }
+    .into();
     assert_eq!(Ok(TermType::Bool), type_check(&ast, &te));
 }
 
 #[test]
 fn test_var_does_not_exist() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::Var("v1".to_string());
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Var("v1".to_string()).into();
     assert_eq!(
         Err("Variable name missing in environment".to_string()),
-        type_check(&ast, &te)
+        type_check(&ast, &te).map_err(|e| e.message)
     );
 }
 
 #[test]
 fn if_test_1() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::IfStmt {
-        test: Box::new(Term::BoolConst(true)),
-        then_body: Box::new(Term::NumConst(1)),
-        else_body: Box::new(Term::NumConst(8)),
-    };
+    let te = TyEnv::default();
+    let ast: Term = TermKind::IfStmt {
+        test: Box::new(TermKind::BoolConst(true).into()),
+        then_body: Box::new(TermKind::NumConst(from_i64(1)).into()),
+        else_body: Box::new(TermKind::NumConst(from_i64(8)).into()),
+    }
+    .into();
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
 
 #[test]
 fn if_test_2() {
-    let te = TyEnv(HashMap::new());
-    let ast = Term::IfStmt {
-        test: Box::new(Term::NumConst(9)),
-        then_body: Box::new(Term::NumConst(1)),
-        else_body: Box::new(Term::NumConst(8)),
-    };
+    let te = TyEnv::default();
+    let ast: Term = TermKind::IfStmt {
+        test: Box::new(TermKind::NumConst(from_i64(9)).into()),
+        then_body: Box::new(TermKind::NumConst(from_i64(1)).into()),
+        else_body: Box::new(TermKind::NumConst(from_i64(8)).into()),
+    }
+    .into();
     assert_eq!(
-        Err("Condition to if must be a boolean".to_string()),
-        type_check(&ast, &te)
+        Err("condition has type Int, expected Bool".to_string()),
+        type_check(&ast, &te).map_err(|e| e.message)
     );
 }
 
 #[test]
 fn test_var_exists() {
     let mut map = HashMap::new();
-    map.insert("v1".to_string(), TermType::Int);
-    let te = TyEnv(map);
-    let ast = Term::Var("v1".to_string());
+    map.insert("v1".to_string(), Scheme::mono(TermType::Int));
+    let te = TyEnv::default().with_vars(map);
+    let ast: Term = TermKind::Var("v1".to_string()).into();
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
 
 #[test]
 fn test_simple_lambda_1() {
-    let math_func = Term::MathOp {
+    let math_func: Term = TermKind::MathOp {
         opr: BinMathOp::Add,
-        t1: Box::new(Term::Var("v1".to_string())),
-        t2: Box::new(Term::NumConst(6)),
-    };
-    let math_func2 = math_func.clone();
+        t1: Box::new(TermKind::Var("v1".to_string()).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
 
-    let te = TyEnv(HashMap::new());
-    let ast = Term::Lambda {
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Lambda {
         var_name: "v1".to_string(),
+        var_type: Some(TypeAnn::Int),
         expr: Box::new(math_func),
-    };
+    }
+    .into();
 
     assert_eq!(
-        Ok(TermType::Func {
-            env: HashMap::new(),
-            name: "v1".to_string(),
-            func_term: Box::new(math_func2),
-        }),
+        Ok(TermType::Arrow(
+            Box::new(TermType::Int),
+            Box::new(TermType::Int)
+        )),
         type_check(&ast, &te)
     );
 }
 
 #[test]
 fn test_apply_1() {
-    let math_expr = Term::MathOp {
+    let math_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Add,
-        t1: Box::new(Term::Var("v1".to_string())),
-        t2: Box::new(Term::NumConst(6)),
-    };
-    let math_func = Term::Lambda {
+        t1: Box::new(TermKind::Var("v1".to_string()).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
+    let math_func: Term = TermKind::Lambda {
         var_name: "v1".to_string(),
+        var_type: Some(TypeAnn::Int),
         expr: Box::new(math_expr),
-    };
+    }
+    .into();
 
-    let te = TyEnv(HashMap::new());
-    let ast = Term::Apply {
-        var_term: Box::new(Term::NumConst(1)),
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Apply {
+        var_term: Box::new(TermKind::NumConst(from_i64(1)).into()),
         function: Box::new(math_func),
-    };
+    }
+    .into();
 
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
@@ -278,24 +1630,669 @@ fn test_apply_1() {
 #[test]
 /// Overrides existing v1
 fn test_apply_2() {
-    let math_expr = Term::MathOp {
+    let math_expr: Term = TermKind::MathOp {
         opr: BinMathOp::Add,
-        t1: Box::new(Term::Var("v1".to_string())),
-        t2: Box::new(Term::NumConst(6)),
-    };
-    let math_func = Term::Lambda {
+        t1: Box::new(TermKind::Var("v1".to_string()).into()),
+        t2: Box::new(TermKind::NumConst(from_i64(6)).into()),
+    }
+    .into();
+    let math_func: Term = TermKind::Lambda {
         var_name: "v1".to_string(),
+        var_type: Some(TypeAnn::Int),
         expr: Box::new(math_expr),
-    };
+    }
+    .into();
 
     let mut map = HashMap::new();
-    map.insert("v1".to_string(), TermType::Bool);
-    let te = TyEnv(map);
+    map.insert("v1".to_string(), Scheme::mono(TermType::Bool));
+    let te = TyEnv::default().with_vars(map);
 
-    let ast = Term::Apply {
-        var_term: Box::new(Term::NumConst(1)),
+    let ast: Term = TermKind::Apply {
+        var_term: Box::new(TermKind::NumConst(from_i64(1)).into()),
         function: Box::new(math_func),
-    };
+    }
+    .into();
 
     assert_eq!(Ok(TermType::Int), type_check(&ast, &te));
 }
+
+#[test]
+fn test_error_span() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "1 + (2 * missing);").unwrap();
+    assert_eq!(
+        Err(TypeError::new(
+            E_UNBOUND_VARIABLE,
+            "Variable name missing in environment",
+            Span {
+                file: 0,
+                start: 9,
+                end: 16
+            }
+        )
+        .with_help("define it with `missing := ...;`")),
+        type_check(&terms[0], &te)
+    );
+}
+
+#[test]
+fn test_lambda_body_checked_at_definition() {
+    let te = TyEnv::default();
+    let ast: Term = TermKind::Lambda {
+        var_name: "b".to_string(),
+        var_type: Some(TypeAnn::Bool),
+        expr: Box::new(
+            TermKind::MathOp {
+                opr: BinMathOp::Add,
+                t1: Box::new(TermKind::Var("b".to_string()).into()),
+                t2: Box::new(TermKind::NumConst(from_i64(1)).into()),
+            }
+            .into(),
+        ),
+    }
+    .into();
+
+    assert_eq!(
+        Err(E_MATH_OPERANDS),
+        type_check(&ast, &te).map_err(|e| e.code)
+    );
+}
+
+#[test]
+fn test_argument_mismatch() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\x: Int. x) true;").unwrap();
+    let err = type_check(&terms[0], &te).unwrap_err();
+    assert_eq!(E_ARGUMENT_MISMATCH, err.code);
+    assert_eq!((13, 17), (err.span.start, err.span.end));
+}
+
+#[test]
+fn test_higher_order() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\f: Int -> Bool. f 1) (\\n: Int. n == 0);").unwrap();
+    assert_eq!(Ok(TermType::Bool), type_check(&terms[0], &te));
+}
+
+#[test]
+fn test_unannotated_lambda() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\x. x + 1);").unwrap();
+    assert_eq!(
+        Ok(TermType::Arrow(
+            Box::new(TermType::Int),
+            Box::new(TermType::Int)
+        )),
+        type_check(&terms[0], &te)
+    );
+}
+
+#[test]
+fn test_generalize_identity() {
+    use parse::parse_file;
+
+    let mut te = TyEnv::default();
+    let terms = parse_file(0, "ident := (\\y. y); ident 1; ident true;").unwrap();
+    let scheme = type_check_definition(&terms[0], &te).unwrap();
+    assert_eq!(
+        Scheme {
+            vars: vec![0],
+            eq_vars: vec![],
+            classes: vec![],
+            ty: TermType::Arrow(Box::new(TermType::Var(0)), Box::new(TermType::Var(0))),
+        },
+        scheme
+    );
+
+    te.vars.insert("ident".to_string(), scheme);
+    assert_eq!(Ok(TermType::Int), type_check(&terms[1], &te));
+    assert_eq!(Ok(TermType::Bool), type_check(&terms[2], &te));
+}
+
+#[test]
+fn test_lambda_parameter_is_monomorphic() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\f. if f true then f 1 else 0 endif);").unwrap();
+    assert_eq!(
+        Err(E_ARGUMENT_MISMATCH),
+        type_check(&terms[0], &te).map_err(|e| e.code)
+    );
+}
+
+#[test]
+fn test_occurs_check() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\f. f f);").unwrap();
+    assert_eq!(
+        Err(E_INFINITE_TYPE),
+        type_check(&terms[0], &te).map_err(|e| e.code)
+    );
+}
+
+#[test]
+fn test_equality_type_variables() {
+    use parse::parse_file;
+
+    let mut te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "eq := (\\a. (\\b. a == b)); eq 1 2; eq (\\x. x) (\\x. x); (\\x. x) == (\\x. x);",
+    )
+    .unwrap();
+    let scheme = type_check_definition(&terms[0], &te).unwrap();
+    assert_eq!(vec![0], scheme.eq_vars);
+
+    te.vars.insert("eq".to_string(), scheme);
+    assert_eq!(Ok(TermType::Bool), type_check(&terms[1], &te));
+    assert_eq!(
+        Err(E_EQUALITY_OPERANDS),
+        type_check(&terms[2], &te).map_err(|e| e.code)
+    );
+    assert_eq!(
+        Err(E_EQUALITY_OPERANDS),
+        type_check(&terms[3], &te).map_err(|e| e.code)
+    );
+}
+
+#[test]
+fn test_display() {
+    let int_to_bool = TermType::Arrow(Box::new(TermType::Int), Box::new(TermType::Bool));
+    assert_eq!("Int -> Bool", int_to_bool.to_string());
+    assert_eq!(
+        "Int -> Bool -> Int",
+        TermType::Arrow(
+            Box::new(TermType::Int),
+            Box::new(TermType::Arrow(
+                Box::new(TermType::Bool),
+                Box::new(TermType::Int)
+            ))
+        )
+        .to_string()
+    );
+    assert_eq!(
+        "(Int -> Bool) -> a1",
+        TermType::Arrow(Box::new(int_to_bool), Box::new(TermType::Var(26))).to_string()
+    );
+}
+
+#[test]
+fn test_mismatch_messages() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let message = |source: &str| {
+        let terms = parse_file(0, source).unwrap();
+        type_check(&terms[0], &te).unwrap_err().message
+    };
+    assert_eq!(
+        "then-branch has type Int -> Int but else-branch has type Bool",
+        message("if true then (\\x. x + 1) else false endif;")
+    );
+    assert_eq!(
+        "left operand of `*` has type Bool, expected Int",
+        message("true * 2;")
+    );
+    assert_eq!(
+        "left side of `!=` has type Int but right side has type a -> a",
+        message("1 != (\\x. x);")
+    );
+    assert_eq!(
+        "values of type a -> a cannot be compared with `==` or `!=`",
+        message("(\\x. x) == (\\y. y);")
+    );
+    assert_eq!(
+        "cannot construct the infinite type a = a -> b",
+        message("(\\f. f f);")
+    );
+
+    let te = TyEnv {
+        arrow: ArrowStyle::Unicode,
+        ..TyEnv::default()
+    };
+    let terms = parse_file(0, "(λf: (Int → Int) → Int. f) 1;").unwrap();
+    assert_eq!(
+        "argument has type Int but the function expects (Int → Int) → Int",
+        type_check(&terms[0], &te).unwrap_err().message
+    );
+}
+
+#[test]
+fn test_compare() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\x. x < 3); 1 >= true;").unwrap();
+    assert_eq!(
+        Ok(TermType::Arrow(
+            Box::new(TermType::Int),
+            Box::new(TermType::Bool)
+        )),
+        type_check(&terms[0], &te)
+    );
+    assert_eq!(
+        Err("right side of `>=` has type Bool, expected Int".to_string()),
+        type_check(&terms[1], &te).map_err(|e| e.message)
+    );
+}
+
+#[test]
+fn test_logic() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(0, "(\\p. !p || p && true); true && 1; !0;").unwrap();
+    assert_eq!(
+        Ok(TermType::Arrow(
+            Box::new(TermType::Bool),
+            Box::new(TermType::Bool)
+        )),
+        type_check(&terms[0], &te)
+    );
+    assert_eq!(
+        Err("right side of `&&` has type Int, expected Bool".to_string()),
+        type_check(&terms[1], &te).map_err(|e| e.message)
+    );
+    assert_eq!(
+        Err("operand of `!` has type Int, expected Bool".to_string()),
+        type_check(&terms[2], &te).map_err(|e| e.message)
+    );
+}
+
+#[test]
+fn test_builtins() {
+    use parse::parse_file;
+
+    let terms = parse_file(0, "max (2 ^ abs 3 % 5); min true; abs;").unwrap();
+    let te = TyEnv::default();
+    assert_eq!(
+        Ok("Int -> Int".to_string()),
+        type_check(&terms[0], &te).map(|t| t.to_string())
+    );
+    assert_eq!(
+        Err("argument has type Bool but the function expects Int".to_string()),
+        type_check(&terms[1], &te).map_err(|e| e.message)
+    );
+    // A definition hides the builtin with the same name.
+    let mut map = HashMap::new();
+    map.insert("abs".to_string(), Scheme::mono(TermType::Bool));
+    assert_eq!(
+        Ok(TermType::Bool),
+        type_check(&terms[2], &TyEnv::default().with_vars(map))
+    );
+}
+
+#[test]
+fn test_let() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "let id = (\\x. x) in id id 1;\
+         let eq = (\\a. (\\b. a == b)) in eq (\\x. x) (\\x. x);\
+         (\\f. let g = f in g 1 == g true);",
+    )
+    .unwrap();
+    assert_eq!(Ok(TermType::Int), type_check(&terms[0], &te));
+    assert_eq!(
+        Err("values of type a -> a cannot be compared with `==` or `!=`".to_string()),
+        type_check(&terms[1], &te).map_err(|e| e.message)
+    );
+    // A lambda parameter is not generalized, even when a let binds it.
+    assert_eq!(
+        Err("argument has type Bool but the function expects Int".to_string()),
+        type_check(&terms[2], &te).map_err(|e| e.message)
+    );
+}
+
+#[test]
+fn test_let_rec() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "let rec len = (\\f. (\\n. if n == 0 then 0 else 1 + len f (n - 1) endif)) \
+         in len (\\b. !b) 3 + len (\\x. x + 1) 4;\
+         let rec f = (\\n. f true + n) in f;\
+         let rec x = x + 1 in x;",
+    )
+    .unwrap();
+    assert_eq!(Ok(TermType::Int), type_check(&terms[0], &te));
+    assert_eq!(
+        Err("`f` is defined with type Int -> Int but used as Bool -> Int".to_string()),
+        type_check(&terms[1], &te).map_err(|e| e.message)
+    );
+    assert_eq!(
+        Err((
+            E_RECURSIVE_VALUE,
+            "`x` is defined recursively, so it must be a function".to_string()
+        )),
+        type_check(&terms[2], &te).map_err(|e| (e.code, e.message))
+    );
+}
+
+#[test]
+fn test_tuples() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "(1, (true, \\x. x + 1));\
+         (\\p: (Int, Bool). p.1);\
+         (\\p. (snd p, fst p));\
+         (1, 2).2;\
+         (\\p. p.0);\
+         (1, true) == (1, 2);",
+    )
+    .unwrap();
+    let check = |i: usize| type_check(&terms[i], &te).map(|t| t.to_string());
+    assert_eq!(Ok("(Int, (Bool, Int -> Int))".to_string()), check(0));
+    assert_eq!(Ok("(Int, Bool) -> Bool".to_string()), check(1));
+    assert_eq!(Ok("(a, b) -> (b, a)".to_string()), check(2));
+    assert_eq!(
+        Err("`.2` is out of range for a tuple of type (Int, Int)".to_string()),
+        check(3).map_err(|e| e.message)
+    );
+    assert_eq!(Err(E_PROJECTION), check(4).map_err(|e| e.code));
+    assert_eq!(
+        Err(
+            "left side of `==` has type (Int, Bool) but right side has type (Int, Int)".to_string()
+        ),
+        check(5).map_err(|e| e.message)
+    );
+}
+
+#[test]
+fn test_records() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "{ x = 1, flag = true, pair = (1, 2) };\
+         (\\r: { x: Int, flag: Bool }. if r.flag then r.x else 0 endif);\
+         (\\r: { x: Int }. r) { x = 1, y = 2, z = 3 };\
+         { x = 1 }.y;\
+         (\\r. r.x);\
+         { f = (\\x. x) } == { f = (\\x. x) };",
+    )
+    .unwrap();
+    let check = |i: usize| type_check(&terms[i], &te).map(|t| t.to_string());
+    assert_eq!(
+        Ok("{ flag: Bool, pair: (Int, Int), x: Int }".to_string()),
+        check(0)
+    );
+    assert_eq!(Ok("{ flag: Bool, x: Int } -> Int".to_string()), check(1));
+    assert_eq!(
+        Err((
+            "argument has type { x: Int, y: Int, z: Int } but the function expects { x: Int }"
+                .to_string(),
+            Some("unknown fields `y` and `z`".to_string())
+        )),
+        check(2).map_err(|e| (e.message, e.help))
+    );
+    assert_eq!(
+        Err((
+            "a record of type { x: Int } has no field `y`".to_string(),
+            Some("it has field `x`".to_string())
+        )),
+        check(3).map_err(|e| (e.message, e.help))
+    );
+    assert_eq!(Err(E_FIELD), check(4).map_err(|e| e.code));
+    assert_eq!(Err(E_EQUALITY_OPERANDS), check(5).map_err(|e| e.code));
+}
+
+#[test]
+fn test_data() {
+    use parse::parse_file;
+
+    let terms = parse_file(
+        0,
+        "data Shape = Circle Int | Rect Int Int | Dot;\
+         data Shapes = Nil | Cons Shape Shapes;\
+         Rect 1;\
+         (\\s: Shape. case s of Circle r -> r | Rect w h -> w * h | Dot -> 0 end);\
+         case Dot of Circle r -> r | Rect w h -> w * h end;\
+         case Dot of Circle r -> r | Rect w -> w | Dot -> 0 end;\
+         case Dot of Dot -> 0 | Dot -> 1 | Circle _ -> 2 | Rect _ _ -> 3 end;\
+         case 1 of Dot -> 0 end;\
+         case Dot of Nil -> 0 | Cons _ _ -> 1 end;\
+         case Dot of Circle r -> r | Rect _ _ -> true | Dot -> 0 end;\
+         case Dot of Square s -> s end;\
+         (\\s: Square. s);\
+         Dot == Dot;",
+    )
+    .unwrap();
+    let mut te = TyEnv::default();
+    let schemes = type_check_data(&[&terms[0], &terms[1]], &te).unwrap();
+    assert_eq!(
+        vec![
+            "Circle: Int -> Shape",
+            "Rect: Int -> Int -> Shape",
+            "Dot: Shape",
+            "Nil: Shapes",
+            "Cons: Shape -> Shapes -> Shapes"
+        ],
+        schemes
+            .iter()
+            .map(|(name, scheme)| format!("{}: {}", name, scheme.ty))
+            .collect::<Vec<_>>()
+    );
+    te.vars.extend(schemes);
+    let check = |i: usize| type_check(&terms[i], &te).map(|t| t.to_string());
+    let error = |i: usize| check(i).map_err(|e| (e.code, e.message));
+    assert_eq!(Ok("Int -> Shape".to_string()), check(2));
+    assert_eq!(Ok("Shape -> Int".to_string()), check(3));
+    assert_eq!(
+        Err((
+            E_NON_EXHAUSTIVE,
+            "`case` does not handle constructor `Dot`".to_string()
+        )),
+        error(4)
+    );
+    assert_eq!(
+        Err((
+            E_CONSTRUCTOR,
+            "`Rect` has 2 fields, but the pattern names 1".to_string()
+        )),
+        error(5)
+    );
+    assert_eq!(
+        Err((E_CONSTRUCTOR, "`Dot` already has an arm".to_string())),
+        error(6)
+    );
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the scrutinee has type Int but `Dot` builds Shape".to_string()
+        )),
+        error(7)
+    );
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the scrutinee has type Shape but `Nil` builds Shapes".to_string()
+        )),
+        error(8)
+    );
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the arm for `Rect` has type Bool but the first arm has type Int".to_string()
+        )),
+        error(9)
+    );
+    assert_eq!(
+        Err((E_CONSTRUCTOR, "`Square` is not a constructor".to_string())),
+        error(10)
+    );
+    assert_eq!(
+        Err((E_UNKNOWN_TYPE, "unknown type `Square`".to_string())),
+        error(11)
+    );
+    assert_eq!(Err(E_EQUALITY_OPERANDS), check(12).map_err(|e| e.code));
+
+    let terms = parse_file(0, "data Int = Big; data T = T Shape; data List = Empty;").unwrap();
+    let error = |i: usize| {
+        type_check_data(&[&terms[i]], &TyEnv::default()).map_err(|e| (e.code, e.message))
+    };
+    assert_eq!(
+        Err((
+            E_DUPLICATE_DEFINITION,
+            "`Int` is already a type".to_string()
+        )),
+        error(0)
+    );
+    assert_eq!(
+        Err((E_UNKNOWN_TYPE, "unknown type `Shape`".to_string())),
+        error(1)
+    );
+    assert_eq!(Err(E_DUPLICATE_DEFINITION), error(2).map_err(|e| e.0));
+}
+
+#[test]
+fn test_match() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "(\\n. match n with 0 -> true | m -> m > 10 end);\
+         (\\b. match b with true -> 1 | _ -> 0 end);\
+         (\\n. match n with 0 -> 1 | true -> 2 end);\
+         (\\n. match n with 0 -> 1 | _ -> false end);",
+    )
+    .unwrap();
+    let check = |i: usize| type_check(&terms[i], &te).map(|t| t.to_string());
+    assert_eq!(Ok("Int -> Bool".to_string()), check(0));
+    assert_eq!(Ok("Bool -> Int".to_string()), check(1));
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the pattern `true` matches Bool but the value it is matched against has type Int"
+                .to_string()
+        )),
+        check(2).map_err(|e| (e.code, e.message))
+    );
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the arm for `_` has type Bool but the first arm has type Int".to_string()
+        )),
+        check(3).map_err(|e| (e.code, e.message))
+    );
+}
+
+#[test]
+fn test_lists() {
+    use parse::parse_file;
+
+    let te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "[];\
+         1 :: [2];\
+         (\\l. match l with [] -> 0 | x :: _ -> x end);\
+         (\\l: List (List Bool). length l);\
+         [1, true];\
+         1 :: [true];\
+         (\\l. match l with [x, x] -> x | _ -> 0 end);\
+         [(\\x. x)] == [];\
+         (\\l. match l with [true] -> 1 | 0 :: _ -> 2 end);",
+    )
+    .unwrap();
+    let check = |i: usize| type_check(&terms[i], &te).map(|t| t.to_string());
+    let error = |i: usize| check(i).map_err(|e| (e.code, e.message));
+    assert_eq!(Ok("List a".to_string()), check(0));
+    assert_eq!(Ok("List Int".to_string()), check(1));
+    assert_eq!(Ok("List Int -> Int".to_string()), check(2));
+    assert_eq!(Ok("List (List Bool) -> Int".to_string()), check(3));
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "element 1 of the list has type Bool but the first has type Int".to_string()
+        )),
+        error(4)
+    );
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the tail of `::` has type List Bool but the head needs List Int".to_string()
+        )),
+        error(5)
+    );
+    assert_eq!(
+        Err((
+            E_DUPLICATE_DEFINITION,
+            "`x` is bound twice in this pattern".to_string()
+        )),
+        error(6)
+    );
+    assert_eq!(Err(E_EQUALITY_OPERANDS), check(7).map_err(|e| e.code));
+    assert_eq!(
+        Err((
+            E_MISMATCH,
+            "the pattern `0` matches Int but the value it is matched against has type Bool"
+                .to_string()
+        )),
+        error(8)
+    );
+}
+
+#[test]
+fn test_strings() {
+    use parse::parse_file;
+
+    let mut te = TyEnv::default();
+    let terms = parse_file(
+        0,
+        "\"a\" ++ show 1 ++ show true;\
+         \"a\" == \"b\";\
+         (length \"abc\", length [1]);\
+         size := (\\s. length s);\
+         size 1;\
+         show \"a\";\
+         \"n\" ++ 1;\
+         (\\s: Str. s);",
+    )
+    .unwrap();
+    let check = |i: usize, te: &TyEnv| type_check(&terms[i], te).map(|t| t.to_string());
+    assert_eq!(Ok("Str".to_string()), check(0, &te));
+    assert_eq!(Ok("Bool".to_string()), check(1, &te));
+    assert_eq!(Ok("(Int, Int)".to_string()), check(2, &te));
+
+    // A function that uses `length` on its parameter keeps its class.
+    let scheme = type_check_definition(&terms[3], &te).unwrap();
+    assert_eq!(vec![(0, Class::Sized)], scheme.classes);
+    te.vars.insert("size".to_string(), scheme);
+    assert_eq!(
+        Err((E_CLASS, "values of type Int have no length".to_string())),
+        check(4, &te).map_err(|e| (e.code, e.message))
+    );
+    assert_eq!(
+        Err((E_CLASS, "values of type Str cannot be shown".to_string())),
+        check(5, &te).map_err(|e| (e.code, e.message))
+    );
+    assert_eq!(
+        Err((
+            E_CONCAT_OPERANDS,
+            "right operand of `++` has type Int, expected Str".to_string()
+        )),
+        check(6, &te).map_err(|e| (e.code, e.message))
+    );
+    assert_eq!(Ok("Str -> Str".to_string()), check(7, &te));
+}
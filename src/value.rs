@@ -1,16 +1,54 @@
 use ast::*;
-use std::collections::HashMap;
+use builtin::Builtin;
+use num::Num;
+use std::collections::BTreeMap;
+use std::rc::Rc;
 
 #[derive(PartialEq, Debug, Clone)]
 /// This enum represents all possible values that a term can evaluate to.
 pub enum Value {
-    Num(u64),
+    Num(Num),
     Bool(bool),
+    /// A string, which prints quoted and with its special characters
+    /// escaped: `Str("a\"b\n")`.
+    Str(String),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    /// The fields of a record, which print in order of their names.
+    Record(BTreeMap<String, Value>),
+    /// A lambda, with the variables it captured. Closures share what they
+    /// captured rather than copying it.
     Closure {
-        env: HashMap<String, Value>,
+        env: Rc<BTreeMap<String, Value>>,
         name: String,
         func_term: Box<Term>,
     },
+    /// A function of a `rec` group, which is applied like a closure. `env`
+    /// does not hold the group itself, so that values stay finite; calling
+    /// the function adds the group to it. Every function of a group shares
+    /// `env` and `group`.
+    Rec {
+        env: Rc<BTreeMap<String, Value>>,
+        group: Rc<[(String, Term)]>,
+        name: String,
+    },
+    /// A builtin applied to fewer arguments than it takes.
+    Builtin {
+        builtin: Builtin,
+        args: Vec<Value>,
+    },
+    /// A constructor applied to fewer arguments than its type has fields.
+    Constructor {
+        name: String,
+        arity: usize,
+        args: Vec<Value>,
+    },
+    /// A value of a data type: the constructor that built it, and its
+    /// fields.
+    Tagged {
+        constructor: String,
+        fields: Vec<Value>,
+    },
     Assignm {
         name: String,
         val: Box<Value>,